@@ -0,0 +1,2 @@
+-- Add migration script here
+ALTER TABLE systems ADD COLUMN split_messages BOOLEAN NOT NULL DEFAULT FALSE;
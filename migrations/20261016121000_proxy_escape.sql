@@ -0,0 +1,3 @@
+-- Add migration script here
+ALTER TABLE systems ADD COLUMN proxy_escape TEXT NOT NULL DEFAULT '\' CHECK (length(trim(proxy_escape)) > 0);
+ALTER TABLE systems ADD COLUMN strip_proxy_escape BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,4 @@
+-- Add migration script here
+-- triggers is STRICT, so these booleans are stored as integers
+ALTER TABLE triggers ADD COLUMN case_sensitive INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE triggers ADD COLUMN ignore_whitespace INTEGER NOT NULL DEFAULT 0;
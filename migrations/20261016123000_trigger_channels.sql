@@ -0,0 +1,9 @@
+-- Add migration script here
+-- 0 = everywhere, 1 = only in trigger_channels, 2 = everywhere except trigger_channels. See trigger.rs
+ALTER TABLE triggers ADD COLUMN channel_scope INTEGER NOT NULL DEFAULT 0;
+
+CREATE TABLE trigger_channels (
+    trigger_id INTEGER NOT NULL REFERENCES triggers (id) ON DELETE CASCADE,
+    channel_id TEXT NOT NULL,
+    PRIMARY KEY (trigger_id, channel_id)
+) STRICT;
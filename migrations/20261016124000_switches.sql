@@ -0,0 +1,12 @@
+-- Add migration script here
+CREATE TABLE switches (
+    id INTEGER NOT NULL PRIMARY KEY,
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    -- NULL when switching to the base account
+    member_id INTEGER REFERENCES members (id),
+    -- What caused the switch. See switch.rs
+    source INTEGER NOT NULL,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+) STRICT;
+
+CREATE INDEX switches_system_id_created_at ON switches (system_id, created_at);
@@ -0,0 +1,25 @@
+-- Add migration script here
+-- Members fronting alongside the primary fronter (systems.currently_fronting_member_id)
+CREATE TABLE co_fronters (
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    member_id INTEGER NOT NULL REFERENCES members (id),
+    PRIMARY KEY (system_id, member_id)
+) STRICT;
+
+CREATE TRIGGER check_co_fronter_is_enabled
+BEFORE INSERT ON co_fronters
+FOR EACH ROW
+BEGIN
+    SELECT
+        RAISE(ABORT, 'Cannot add a disabled member as a co-fronter')
+    WHERE EXISTS (
+        SELECT 1 FROM members WHERE id = NEW.member_id AND enabled = FALSE
+    );
+END;
+
+-- The co-fronters at the time of each switch, alongside switches.member_id
+CREATE TABLE switch_co_fronters (
+    switch_id INTEGER NOT NULL REFERENCES switches (id),
+    member_id INTEGER NOT NULL REFERENCES members (id),
+    PRIMARY KEY (switch_id, member_id)
+) STRICT;
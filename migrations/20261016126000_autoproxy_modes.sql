@@ -0,0 +1,9 @@
+-- Add migration script here
+-- How messages without a trigger are proxied. 0 = off, 1 = front, 2 = latch, 3 = member. See system.rs
+ALTER TABLE systems ADD COLUMN autoproxy_mode INTEGER NOT NULL DEFAULT 1;
+
+-- The member messages are always sent as, in member mode
+ALTER TABLE systems ADD COLUMN autoproxy_member_id INTEGER REFERENCES members (id);
+
+-- The member who last used a trigger, in latch mode
+ALTER TABLE systems ADD COLUMN latch_member_id INTEGER REFERENCES members (id);
@@ -0,0 +1,13 @@
+-- Add migration script here
+-- Per-channel autoproxy settings, used instead of the system's in that channel
+CREATE TABLE channel_autoproxy (
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    channel_id TEXT NOT NULL,
+    -- Same values as systems.autoproxy_mode
+    mode INTEGER NOT NULL,
+    -- The member messages are always sent as, in member mode
+    member_id INTEGER REFERENCES members (id),
+    -- The member who last used a trigger in this channel, in latch mode
+    latch_member_id INTEGER REFERENCES members (id),
+    PRIMARY KEY (system_id, channel_id)
+) STRICT;
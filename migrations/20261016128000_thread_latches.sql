@@ -0,0 +1,13 @@
+-- Add migration script here
+-- How long a thread latch lasts without being used, in seconds. NULL turns thread latching off
+ALTER TABLE systems ADD COLUMN thread_latch_timeout INTEGER;
+
+-- The member who last used a trigger in each thread
+CREATE TABLE thread_latches (
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    channel_id TEXT NOT NULL,
+    thread_ts TEXT NOT NULL,
+    member_id INTEGER NOT NULL REFERENCES members (id),
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (system_id, channel_id, thread_ts)
+) STRICT;
@@ -0,0 +1,15 @@
+-- Add migration script here
+-- How long a system can go without sending a message before the front reverts to the base account, in seconds.
+-- NULL turns inactivity reverts off
+ALTER TABLE systems ADD COLUMN inactivity_timeout INTEGER;
+
+-- Pending reverts to the base account. A system has at most one of each kind, recorded as the switch source
+-- (2 = timed switch, 3 = inactivity)
+CREATE TABLE scheduled_reverts (
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    source INTEGER NOT NULL,
+    revert_at TEXT NOT NULL,
+    PRIMARY KEY (system_id, source)
+) STRICT;
+
+CREATE INDEX scheduled_reverts_revert_at ON scheduled_reverts (revert_at);
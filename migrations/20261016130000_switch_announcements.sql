@@ -0,0 +1,6 @@
+-- Add migration script here
+-- The channel switches are announced in. NULL turns announcements off
+ALTER TABLE systems ADD COLUMN announcement_channel_id TEXT;
+
+-- Whether switches from triggers are announced too
+ALTER TABLE systems ADD COLUMN announce_trigger_switches BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,18 @@
+-- Add migration script here
+-- Whether the owner's Slack status shows who's fronting
+ALTER TABLE systems ADD COLUMN mirror_status BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Whether the owner's Slack display name is changed to the primary fronter's too
+ALTER TABLE systems ADD COLUMN mirror_display_name BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- The status emoji used while mirroring
+ALTER TABLE systems ADD COLUMN status_emoji TEXT NOT NULL DEFAULT ':busts_in_silhouette:';
+
+-- The owner's own status (and display name, if that's mirrored too) from before mirroring started,
+-- put back when they switch to their base account
+CREATE TABLE saved_statuses (
+    system_id INTEGER PRIMARY KEY NOT NULL REFERENCES systems (id),
+    status_text TEXT NOT NULL,
+    status_emoji TEXT NOT NULL,
+    display_name TEXT
+) STRICT;
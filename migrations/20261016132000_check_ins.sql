@@ -0,0 +1,12 @@
+-- Add migration script here
+-- How long the front can stay the same before the owner is sent a check-in reminder, in seconds.
+-- NULL turns reminders off
+ALTER TABLE systems ADD COLUMN check_in_interval INTEGER;
+
+-- When each system's next check-in reminder is due
+CREATE TABLE scheduled_check_ins (
+    system_id INTEGER PRIMARY KEY NOT NULL REFERENCES systems (id),
+    remind_at TEXT NOT NULL
+) STRICT;
+
+CREATE INDEX scheduled_check_ins_remind_at ON scheduled_check_ins (remind_at);
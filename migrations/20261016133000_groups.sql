@@ -0,0 +1,20 @@
+-- Add migration script here
+-- Named sets of members, e.g. a subsystem
+CREATE TABLE groups (
+    id INTEGER NOT NULL PRIMARY KEY,
+    system_id INTEGER NOT NULL REFERENCES systems (id),
+    -- Used to refer to the group in commands, so it's unique within the system
+    name TEXT NOT NULL,
+    description TEXT,
+    icon_url TEXT,
+    created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (system_id, name)
+) STRICT;
+
+CREATE TABLE group_members (
+    group_id INTEGER NOT NULL REFERENCES groups (id),
+    member_id INTEGER NOT NULL REFERENCES members (id),
+    PRIMARY KEY (group_id, member_id)
+) STRICT;
+
+CREATE INDEX group_members_member_id ON group_members (member_id);
@@ -0,0 +1,6 @@
+-- Add migration script here
+-- Shown after every member's name on proxied messages, e.g. "| Sunflower System"
+ALTER TABLE systems ADD COLUMN tag TEXT;
+
+-- Lets a member opt out of having the system tag after their name
+ALTER TABLE members ADD COLUMN show_system_tag BOOLEAN NOT NULL DEFAULT TRUE;
@@ -0,0 +1,16 @@
+-- Add migration script here
+-- Markdown shown on the member's profile
+ALTER TABLE members ADD COLUMN description TEXT;
+-- Accent colour, as a hex code like #ff8800
+ALTER TABLE members ADD COLUMN color TEXT;
+-- As YYYY-MM-DD, from Slack's date picker
+ALTER TABLE members ADD COLUMN birthday TEXT;
+
+-- Arbitrary name/value pairs shown on the member's profile, in order
+CREATE TABLE member_custom_fields (
+    member_id INTEGER NOT NULL REFERENCES members (id),
+    position INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL,
+    PRIMARY KEY (member_id, position)
+) STRICT;
@@ -0,0 +1,12 @@
+-- Add migration script here
+-- 0 = public, 1 = private. See `Privacy`
+-- Whether other users can see the member at all
+ALTER TABLE members ADD COLUMN privacy INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE members ADD COLUMN name_privacy INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE members ADD COLUMN pronoun_privacy INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE members ADD COLUMN description_privacy INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE members ADD COLUMN birthday_privacy INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE members ADD COLUMN custom_fields_privacy INTEGER NOT NULL DEFAULT 0;
+
+ALTER TABLE systems ADD COLUMN front_privacy INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE systems ADD COLUMN member_list_privacy INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,18 @@
+-- Add migration script here
+-- Avatars uploaded from Slack files, served from /avatar/{member_id}
+CREATE TABLE member_avatars (
+    member_id INTEGER PRIMARY KEY NOT NULL REFERENCES members (id),
+    -- The Slack file the avatar was uploaded from
+    slack_file_id TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    data BLOB NOT NULL,
+    updated_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+) STRICT;
+
+-- DMs asking the owner to reply with an image for a member's avatar
+CREATE TABLE avatar_requests (
+    channel_id TEXT NOT NULL,
+    message_ts TEXT NOT NULL,
+    member_id INTEGER NOT NULL REFERENCES members (id),
+    PRIMARY KEY (channel_id, message_ts)
+) STRICT;
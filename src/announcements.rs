@@ -0,0 +1,105 @@
+//! Announcing switches in a system's announcement channel, so the people around a system know who's fronting.
+//!
+//! Announcements are best-effort: failing to post one is logged, but never fails the switch itself.
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{debug, error};
+
+use crate::{
+    BOT_TOKEN,
+    models::{switch, system, trust::Trusted},
+};
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum AnnounceError {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while posting the announcement to Slack
+    Slack,
+}
+
+/// Announces a system's current front in its announcement channel, if it has one.
+/// Meant to be called after a switch that changed the front
+#[tracing::instrument(skip(client, db))]
+pub async fn announce_switch(
+    client: &SlackHyperClient,
+    system_id: system::Id<Trusted>,
+    source: switch::Source,
+    db: &SqlitePool,
+) {
+    if let Err(error) = try_announce_switch(client, system_id, source, db).await {
+        error!(?error, "Error announcing switch");
+    }
+}
+
+async fn try_announce_switch(
+    client: &SlackHyperClient,
+    system_id: system::Id<Trusted>,
+    source: switch::Source,
+    db: &SqlitePool,
+) -> Result<(), AnnounceError> {
+    let system = system_id
+        .fetch(db)
+        .await
+        .change_context(AnnounceError::Sqlx)?;
+
+    let Some(channel_id) = system.announcement_channel() else {
+        debug!("System has no announcement channel");
+        return Ok(());
+    };
+
+    if source == switch::Source::Trigger && !system.announce_trigger_switches {
+        debug!("Not announcing switch from a trigger");
+        return Ok(());
+    }
+
+    let fronting_members = system_id
+        .fronting_members(db)
+        .await
+        .change_context(AnnounceError::Sqlx)?;
+
+    // The announcement is posted as the primary fronter. Without one, it's posted as the bot
+    let request = match fronting_members.as_slice() {
+        [] => {
+            let owner: SlackUserId = system.owner_id.into();
+
+            SlackApiChatPostMessageRequest::new(
+                channel_id,
+                SlackMessageContent::new()
+                    .with_text(format!("<@{owner}> is back on their base account")),
+            )
+        }
+        [primary, co_fronters @ ..] => {
+            let text = if co_fronters.is_empty() {
+                format!("{} is now fronting", primary.display_name)
+            } else {
+                format!(
+                    "{} is now fronting, with {}",
+                    primary.display_name,
+                    co_fronters
+                        .iter()
+                        .map(|member| member.display_name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            };
+
+            SlackApiChatPostMessageRequest::new(
+                channel_id,
+                SlackMessageContent::new().with_text(text),
+            )
+            .with_username(primary.display_name.clone())
+            .opt_icon_url(primary.profile_picture_url.clone())
+        }
+    };
+
+    client
+        .open_session(&BOT_TOKEN)
+        .chat_post_message(&request)
+        .await
+        .change_context(AnnounceError::Slack)?;
+
+    Ok(())
+}
@@ -0,0 +1,248 @@
+//! Member avatars uploaded from Slack files, so owners don't have to host images themselves.
+//!
+//! `/members avatar` DMs the owner, and an image sent as a reply to that DM becomes the member's avatar.
+//! An image can also be uploaded in the member add/edit modal.
+//! The image is stored in the database and served from `/avatar/{member_id}`, which is used as the profile picture URL.
+
+use axum::{
+    body::Body,
+    extract::{Path, Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use error_stack::{Result, ResultExt, bail, ensure};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{debug, error, warn};
+
+use crate::{
+    BOT_TOKEN, env,
+    models::{
+        avatar::{Avatar, AvatarRequest},
+        member, system,
+        trust::Trusted,
+        user,
+    },
+};
+
+/// Slack won't use a larger image as a message icon anyway
+pub const MAX_AVATAR_SIZE: u64 = 2 * 1024 * 1024;
+
+/// The action ID of the file input in the member modal
+pub const AVATAR_INPUT_ACTION_ID: &str = "avatar_file";
+
+/// Interaction payloads are only ever a few KB, even for big modals
+const MAX_INTERACTION_BODY_SIZE: usize = 1024 * 1024;
+
+/// The image uploaded in the member modal's file input, if any. See [`capture_modal_avatar`]
+#[derive(Debug, Clone, Default)]
+pub struct ModalAvatar(pub Option<SlackFile>);
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while calling the Slack API
+    Slack,
+    /// Couldn't download the image from Slack
+    Download,
+    /// Avatars can be at most 2 MB
+    TooLarge,
+    /// Avatars must be PNG, JPEG or GIF images
+    UnsupportedFormat,
+}
+
+/// Works out the image format from the file's first bytes, rather than trusting its name
+fn detect_content_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if data.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else {
+        None
+    }
+}
+
+/// Sets a member's avatar if the message is a reply to one of the system's avatar requests.
+///
+/// Returns whether it was, in which case the message shouldn't be proxied
+#[tracing::instrument(skip(client, origin, content, db))]
+pub async fn handle_reply(
+    client: &SlackHyperClient,
+    system_id: system::Id<Trusted>,
+    channel_id: &SlackChannelId,
+    origin: &SlackMessageOrigin,
+    content: &SlackMessageContent,
+    db: &SqlitePool,
+) -> Result<bool, Error> {
+    let Some(thread_ts) = &origin.thread_ts else {
+        return Ok(false);
+    };
+
+    let Some(file) = content.files.iter().flatten().next() else {
+        return Ok(false);
+    };
+
+    let Some(member_id) = AvatarRequest::fetch_member_id(channel_id, thread_ts, system_id, db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        return Ok(false);
+    };
+
+    debug!(%member_id, "Received avatar upload");
+
+    let text = match set_avatar(file, member_id, db).await {
+        Ok(()) => {
+            let member = member_id.fetch(db).await.change_context(Error::Sqlx)?;
+            format!("Updated {}'s avatar!", member.display_name)
+        }
+        Err(error) => {
+            warn!(?error, "Failed to set avatar");
+            format!("Couldn't set the avatar: {}", error.current_context())
+        }
+    };
+
+    client
+        .open_session(&BOT_TOKEN)
+        .chat_post_message(
+            &SlackApiChatPostMessageRequest::new(
+                channel_id.clone(),
+                SlackMessageContent::new().with_text(text),
+            )
+            .with_thread_ts(thread_ts.clone()),
+        )
+        .await
+        .change_context(Error::Slack)?;
+
+    Ok(true)
+}
+
+/// Picks the uploaded file out of an interaction's raw payload, and passes it on as a [`ModalAvatar`].
+///
+/// slack-morphism doesn't keep the `files` of a file input in the view state, so this runs before the
+/// interaction is parsed. The body is passed on unchanged, so its signature is still checked as usual.
+pub async fn capture_modal_avatar(request: Request, next: Next) -> Response {
+    let (parts, body) = request.into_parts();
+
+    let Ok(bytes) = axum::body::to_bytes(body, MAX_INTERACTION_BODY_SIZE).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+
+    let file = url::form_urlencoded::parse(&bytes)
+        .find(|(key, _)| key == "payload")
+        .and_then(|(_, payload)| serde_json::from_str(&payload).ok())
+        .and_then(|payload| modal_avatar(&payload));
+
+    let mut request = Request::from_parts(parts, Body::from(bytes));
+    request.extensions_mut().insert(ModalAvatar(file));
+
+    next.run(request).await
+}
+
+fn modal_avatar(payload: &serde_json::Value) -> Option<SlackFile> {
+    let file = payload["view"]["state"]["values"]
+        .as_object()?
+        .values()
+        .find_map(|actions| actions.get(AVATAR_INPUT_ACTION_ID))?
+        .get("files")?
+        .get(0)?;
+
+    serde_json::from_value(file.clone()).ok()
+}
+
+/// Downloads and checks the file, then makes it the member's avatar
+pub async fn set_avatar(
+    file: &SlackFile,
+    member_id: member::Id<Trusted>,
+    db: &SqlitePool,
+) -> Result<(), Error> {
+    let Some(url) = file.url_private_download.clone() else {
+        bail!(Error::Download);
+    };
+
+    // Slack's file events don't say how big the file is, but they do say what it is,
+    // so anything that obviously isn't an image isn't downloaded at all
+    ensure!(
+        file.mimetype
+            .as_ref()
+            .is_none_or(|mimetype| mimetype.0.starts_with("image/")),
+        Error::UnsupportedFormat
+    );
+
+    // Private file URLs need the bot token, which also needs the files:read scope
+    let mut response = reqwest::Client::new()
+        .get(url)
+        .bearer_auth(&BOT_TOKEN.token_value.0)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .change_context(Error::Download)?;
+
+    ensure!(
+        response
+            .content_length()
+            .is_none_or(|length| length <= MAX_AVATAR_SIZE),
+        Error::TooLarge
+    );
+
+    // The length header is optional, so the download is also stopped as soon as it goes over the limit
+    let mut data = Vec::new();
+
+    while let Some(chunk) = response.chunk().await.change_context(Error::Download)? {
+        ensure!(
+            (data.len() + chunk.len()) as u64 <= MAX_AVATAR_SIZE,
+            Error::TooLarge
+        );
+        data.extend_from_slice(&chunk);
+    }
+
+    let Some(content_type) = detect_content_type(&data) else {
+        bail!(Error::UnsupportedFormat);
+    };
+
+    // The version makes Slack fetch the new image instead of using the one it cached
+    let profile_picture_url = format!(
+        "{}/avatar/{}?v={}",
+        env::base_url(),
+        member_id,
+        time::OffsetDateTime::now_utc().unix_timestamp()
+    );
+
+    Avatar::save(
+        member_id,
+        &file.id.0,
+        content_type,
+        &data,
+        &profile_picture_url,
+        db,
+    )
+    .await
+    .change_context(Error::Sqlx)
+}
+
+/// Serves a member's uploaded avatar
+#[tracing::instrument(skip(state))]
+pub async fn avatar_handler(
+    Path(member_id): Path<i64>,
+    State(state): State<user::State>,
+) -> Response {
+    match Avatar::fetch(member_id, &state.db).await {
+        Ok(Some(avatar)) => (
+            [
+                (header::CONTENT_TYPE, avatar.content_type),
+                (header::CACHE_CONTROL, "public, max-age=86400".to_string()),
+            ],
+            avatar.data,
+        )
+            .into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(error) => {
+            error!(?error, "Error fetching avatar");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use std::sync::Arc;
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use tracing::{debug, info, trace};
+
+use crate::{
+    BOT_TOKEN, fetch_member, fetch_system,
+    models::{
+        self,
+        group::{self, GroupRef},
+        member::MemberRef,
+        user,
+    },
+};
+
+#[derive(clap::Subcommand, Debug)]
+#[clap(verbatim_doc_comment)]
+/// A group is a named set of members in your system, like a subsystem.
+///
+/// A member can be in any number of groups.
+///
+/// Also see:
+/// - /members list --group to list the members in a group
+pub enum Group {
+    /// Adds a new group to your system. Expect a popup to fill in the group info!
+    Add,
+    /// Edits a group's info
+    ///
+    /// Expect a popup to edit the info!
+    Edit {
+        /// The group to edit. Use either its name or its ID
+        group: GroupRef,
+    },
+    /// Adds members to a group
+    AddMember {
+        /// The group to add the members to. Use either its name or its ID
+        group: GroupRef,
+        /// The members to add. Use either an alias or a member ID for each
+        #[clap(required = true)]
+        members: Vec<MemberRef>,
+    },
+    /// Removes members from a group
+    RemoveMember {
+        /// The group to remove the members from. Use either its name or its ID
+        group: GroupRef,
+        /// The members to remove. Use either an alias or a member ID for each
+        #[clap(required = true)]
+        members: Vec<MemberRef>,
+    },
+    /// Lists all of your system's groups
+    List,
+    /// Gets info about a group, including its members
+    Info {
+        /// The group to get info about. Use either its name or its ID
+        group: GroupRef,
+    },
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum CommandError {
+    /// Error while calling the Slack API
+    SlackApi,
+    /// Error while calling the database
+    Sqlx,
+}
+
+#[macro_export]
+/// Fetches the group ID associated with the group reference in the given system.
+/// Also attaches the group ID to context
+///
+/// Else, returns early with a warning message
+macro_rules! fetch_group {
+    ($group_ref:expr, $user_state:expr, $system_id:expr => $group_var_name:ident) => {
+        let Some($group_var_name) = $group_ref
+            .validate_by_system($system_id, &$user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?
+        else {
+            use slack_morphism::prelude::*;
+            ::tracing::debug!("User does not have a group {:?} that is associated with the system", $group_ref);
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("The group does not exist! Make sure you spelt the name correctly or used the correct ID.".to_string()),
+            ));
+        };
+
+        $crate::fields!(group_id = %$group_var_name);
+        ::tracing::debug!("Fetched group");
+    };
+}
+
+impl Group {
+    #[tracing::instrument(skip_all)]
+    pub async fn run(
+        self,
+        event: SlackCommandEvent,
+        client: Arc<SlackHyperClient>,
+        state: SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running groups command");
+        match self {
+            Self::Add => Self::create_group(event, &client, &state).await,
+            Self::Edit { group } => Self::edit_group(event, &client, &state, group).await,
+            Self::AddMember { group, members } => {
+                Self::change_members(event, &state, group, members, true).await
+            }
+            Self::RemoveMember { group, members } => {
+                Self::change_members(event, &state, group, members, false).await
+            }
+            Self::List => Self::list_groups(event, &state).await,
+            Self::Info { group } => Self::group_info(event, &state, group).await,
+        }
+    }
+
+    #[tracing::instrument(skip(event, client, state), fields(system_id))]
+    async fn create_group(
+        event: SlackCommandEvent,
+        client: &SlackHyperClient,
+        state: &SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running group add command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        // The view submission would fail without a system, so check before opening it
+        fetch_system!(event, user_state => system_id);
+
+        let view = client
+            .open_session(&BOT_TOKEN)
+            .views_open(&SlackApiViewsOpenRequest::new(
+                event.trigger_id.clone(),
+                group::View::create_add_view(),
+            ))
+            .await
+            .attach_printable("Error opening view")
+            .change_context(CommandError::SlackApi)?;
+
+        info!(view_id = %view.view.state_params.id, "Successfully opened group add view");
+
+        Ok(SlackCommandEventResponse::new(SlackMessageContent::new()))
+    }
+
+    #[tracing::instrument(skip(event, client, state), fields(system_id, group_id))]
+    async fn edit_group(
+        event: SlackCommandEvent,
+        client: &SlackHyperClient,
+        state: &SlackClientEventsUserState,
+        group_ref: GroupRef,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running group edit command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_group!(group_ref, user_state, system_id => group_id);
+
+        let group = group_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let view = client
+            .open_session(&BOT_TOKEN)
+            .views_open(&SlackApiViewsOpenRequest::new(
+                event.trigger_id.clone(),
+                group::View::from(group).create_edit_view(group_id),
+            ))
+            .await
+            .attach_printable("Error opening view")
+            .change_context(CommandError::SlackApi)?;
+
+        info!(view_id = %view.view.state_params.id, "Successfully opened group edit view");
+
+        Ok(SlackCommandEventResponse::new(SlackMessageContent::new()))
+    }
+
+    /// Adds members to a group, or removes them if `add` is false
+    #[tracing::instrument(skip(event, state), fields(system_id, group_id))]
+    async fn change_members(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        group_ref: GroupRef,
+        member_refs: Vec<MemberRef>,
+        add: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Changing group members");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_group!(group_ref, user_state, system_id => group_id);
+
+        let mut member_ids = Vec::with_capacity(member_refs.len());
+
+        for member_ref in &member_refs {
+            fetch_member!(member_ref, user_state, system_id => member_id);
+
+            if !member_ids.contains(&member_id) {
+                member_ids.push(member_id);
+            }
+        }
+
+        let requested = member_ids.len();
+
+        let mut changed = 0;
+
+        for member_id in member_ids {
+            let member_changed = if add {
+                group_id.add_member(member_id, &user_state.db).await
+            } else {
+                group_id.remove_member(member_id, &user_state.db).await
+            }
+            .change_context(CommandError::Sqlx)?;
+
+            if member_changed {
+                changed += 1;
+            }
+        }
+
+        let group = group_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        // Only counts the rows that were actually inserted or deleted
+        let unchanged = requested - changed;
+
+        let text = match (add, unchanged) {
+            (true, 0) => format!("Added {changed} member(s) to {}.", group.name),
+            (true, _) => format!(
+                "Added {changed} member(s) to {}. {unchanged} were already in it.",
+                group.name
+            ),
+            (false, 0) => format!("Removed {changed} member(s) from {}.", group.name),
+            (false, _) => format!(
+                "Removed {changed} member(s) from {}. {unchanged} weren't in it.",
+                group.name
+            ),
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(text),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn list_groups(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Listing groups");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let groups = models::Group::fetch_by_system_id(system_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        if groups.is_empty() {
+            debug!("No groups found");
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("No groups found. Make one with `/groups add`".into()),
+            ));
+        }
+
+        debug!(len = groups.len(), "Found groups");
+
+        let group_blocks = groups
+            .into_iter()
+            .map(|summary| {
+                let fields = [
+                    Some(md!("*Group ID*: {}", summary.group.id)),
+                    Some(md!("*Members*: {}", summary.member_count)),
+                    summary
+                        .group
+                        .description
+                        .map(|description| md!("{}", description)),
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+
+                SlackSectionBlock::new()
+                    .with_text(md!("*{}*", summary.group.name))
+                    .with_fields(fields)
+            })
+            .map(Into::into)
+            .collect();
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(group_blocks),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id, group_id))]
+    async fn group_info(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        group_ref: GroupRef,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running group info command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_group!(group_ref, user_state, system_id => group_id);
+
+        let group = group_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let members = group_id
+            .members(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let member_list = if members.is_empty() {
+            format!(
+                "No members yet. Add some with `/groups add-member {} <members>`",
+                group.name
+            )
+        } else {
+            members
+                .iter()
+                .map(|member| {
+                    format!(
+                        "• {} (ID: {}){}",
+                        member.display_name,
+                        member.id,
+                        if member.enabled { "" } else { " - disabled" }
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+
+        let blocks = slack_blocks![
+            some_into(SlackHeaderBlock::new(group.name.into())),
+            some_into(SlackDividerBlock::new()),
+            some_into(
+                SlackSectionBlock::new()
+                    .with_text(md!(
+                        "{}\n*Group ID*: {}",
+                        group.description.unwrap_or_default(),
+                        group.id
+                    ))
+                    .opt_accessory(group.icon_url.and_then(|url| Some(
+                        SlackSectionBlockElement::Image(SlackBlockImageElement::new(
+                            url.parse().ok()?,
+                            "Group icon".into()
+                        ))
+                    )))
+            ),
+            some_into(SlackSectionBlock::new().with_text(md!(
+                "*Members ({})*\n{}",
+                members.len(),
+                member_list
+            )))
+        ];
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(blocks),
+        ))
+    }
+}
@@ -3,16 +3,22 @@ use std::sync::Arc;
 use error_stack::{Result, ResultExt, report};
 use futures::TryStreamExt;
 use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
 use tracing::{debug, info, trace};
 
 use crate::{
-    BOT_TOKEN, fetch_member, fetch_system, fields,
+    BOT_TOKEN, announcements, fetch_group, fetch_member, fetch_system, fields,
     models::{
         self,
+        avatar::AvatarRequest,
+        group::{self, GroupRef},
         member::{self, MemberRef, View},
-        trust::Untrusted,
+        revert::ScheduledRevert,
+        switch, system,
+        trust::{Trusted, Untrusted},
         user,
     },
+    status, util,
 };
 
 #[derive(clap::Subcommand, Debug)]
@@ -29,6 +35,7 @@ use crate::{
 /// Also see:
 /// - /triggers to manage member triggers (Custom prefixes/suffixes that will automatically message under a specific member profile) \n
 /// - /aliases to manage member aliases (Custom names that can be used to refer to the member in commands)
+/// - /groups to organise members into groups (e.g. subsystems)
 pub enum Member {
     /// Adds a new member to your system. Expect a popup to fill in the member info!
     Add,
@@ -50,6 +57,35 @@ pub enum Member {
         /// The member to enable
         member: member::Id<Untrusted>,
     },
+    /// Sets whether your system tag is shown after a member's name on their messages
+    ///
+    /// The tag is shown for every member by default. Set it with `/system tag`.
+    SystemTag {
+        /// The member to change
+        member: MemberRef,
+        /// Whether the tag is shown after their name
+        #[clap(action = clap::ArgAction::Set)]
+        show: bool,
+    },
+    /// Shows or changes who can see a member and their info. Run with just the member to see the current settings
+    ///
+    /// Everything is public by default. Private info is only shown to you: other users still see the member's display name
+    /// and profile picture on their messages. A private member is left out of `/members list` for other users,
+    /// and message info only shows their display name (e.g. `/members privacy alex --member private`).
+    Privacy {
+        /// The member to change
+        member: MemberRef,
+        #[clap(flatten)]
+        changes: member::PrivacyChanges,
+    },
+    /// Uploads an image as a member's avatar, instead of setting a profile picture URL
+    ///
+    /// I'll DM you: reply to that message with the image (a PNG, JPEG or GIF up to 2 MB).
+    /// Setting a profile picture URL with `/members edit` replaces the uploaded avatar.
+    Avatar {
+        /// The member to upload an avatar for
+        member: MemberRef,
+    },
     /// Gets info about a member
     ///
     /// This will display information about the member, including their name, pronouns, and other details.
@@ -64,6 +100,9 @@ pub enum Member {
     List {
         /// The system to list members from. If left blank, defaults to your system.
         system: Option<String>,
+        /// Only list the members in this group. Use either its name or its ID
+        #[clap(long)]
+        group: Option<GroupRef>,
     },
     /// Edits a member's info
     ///
@@ -72,22 +111,58 @@ pub enum Member {
         /// The member to edit.
         member_id: MemberRef,
     },
-    /// Switch to a different member
+    /// Switch to a different member, or several co-fronting members
     ///
     /// You can switch to a different member by providing their ID or username.
+    /// Giving several members (e.g. `/members switch a b c`) makes them co-front. The first one is the primary fronter,
+    /// who messages are sent as when no trigger is used.
     /// Alternatively, you can use `/members switch --base` to revert to your base account,
     /// and the bot will not rewrite messages under a member profile.
+    /// Use `--for` to switch back to the base account automatically (e.g. `/members switch a --for 2h`).
     #[group(required = true)]
     Switch {
-        /// The member to switch to.
+        /// The members to switch to. The first one is the primary fronter
         #[clap(group = "member")]
-        member_id: Option<MemberRef>,
+        member_ids: Vec<MemberRef>,
+        /// Add a member to the current front, as a co-fronter. Can be given more than once
+        #[clap(long, group = "member")]
+        add: Vec<MemberRef>,
+        /// Remove a member from the current front. Can be given more than once.
+        /// If the primary fronter is removed, the next co-fronter takes their place
+        #[clap(long, group = "member")]
+        remove: Vec<MemberRef>,
         /// Don't switch to another member, just message with the base account
         #[clap(long, short, action, group = "member", alias = "none")]
         base: bool,
+        /// Switch back to the base account after this long (e.g. `30m`, `2h`). Switching again before then cancels it
+        #[clap(long = "for", value_name = "DURATION", value_parser = util::parse_duration, conflicts_with = "base")]
+        duration: Option<time::Duration>,
+    },
+    /// Lists who fronted when, newest first
+    ///
+    /// Every switch is recorded, whether it's from `/members switch` or a trigger.
+    History {
+        /// Only list switches from this long ago (e.g. `12h`, `7d`, `2w`)
+        #[clap(long, value_parser = util::parse_duration)]
+        since: Option<time::Duration>,
+        /// Only list switches to this member, including ones where they co-fronted
+        #[clap(long)]
+        member: Option<MemberRef>,
     },
 }
 
+/// How `/members switch` changes the front
+#[derive(Debug)]
+enum FrontChange {
+    /// Replace everyone fronting. Empty for the base account
+    Set(Vec<MemberRef>),
+    Add(Vec<MemberRef>),
+    Remove(Vec<MemberRef>),
+}
+
+/// The most switches `/members history` lists, to stay within Slack's block limit
+const MAX_HISTORY_SWITCHES: u32 = 40;
+
 #[derive(thiserror::Error, displaydoc::Display, Debug)]
 pub enum CommandError {
     /// Error while calling the Slack API
@@ -113,23 +188,46 @@ impl Member {
             }
             Self::Disable { member } => Self::disable(event, &state, member).await,
             Self::Enable { member } => Self::enable(event, &state, member).await,
+            Self::SystemTag { member, show } => Self::system_tag(event, &state, member, show).await,
+            Self::Privacy { member, changes } => {
+                Self::privacy(event, &state, member, changes).await
+            }
+            Self::Avatar { member } => Self::request_avatar(event, &client, &state, member).await,
             Self::Info { member_id } => Self::member_info(event, &state, member_id).await,
             Self::Edit { member_id } => {
                 Self::edit_member(event, client.open_session(&BOT_TOKEN), &state, member_id).await
             }
-            Self::List { system } => Self::list_members(event, state, system).await,
-            Self::Switch { member_id, base } => {
-                Self::switch_member(event, state, member_id, base).await
+            Self::List { system, group } => Self::list_members(event, state, system, group).await,
+            Self::Switch {
+                member_ids,
+                add,
+                remove,
+                base,
+                duration,
+            } => {
+                let change = if !add.is_empty() {
+                    FrontChange::Add(add)
+                } else if !remove.is_empty() {
+                    FrontChange::Remove(remove)
+                } else if base {
+                    FrontChange::Set(Vec::new())
+                } else {
+                    FrontChange::Set(member_ids)
+                };
+
+                Self::switch_member(event, &client, state, change, duration).await
             }
+            Self::History { since, member } => Self::history(event, &state, since, member).await,
         }
     }
 
-    #[tracing::instrument(skip(event, state), fields(system_id))]
+    #[tracing::instrument(skip(event, client, state), fields(system_id))]
     async fn switch_member(
         event: SlackCommandEvent,
+        client: &SlackHyperClient,
         state: SlackClientEventsUserState,
-        member_ref: Option<MemberRef>,
-        base: bool,
+        change: FrontChange,
+        duration: Option<time::Duration>,
     ) -> Result<SlackCommandEventResponse, CommandError> {
         trace!("Switching member");
         let states = state.read().await;
@@ -137,16 +235,21 @@ impl Member {
 
         fetch_system!(event, user_state => system_id);
 
-        let new_active_member_id = if base {
-            None
-        } else {
-            debug!(requested_member_id = ?&member_ref, "Validating member ID");
-            fetch_member!(member_ref.as_ref().unwrap(), user_state, system_id => member_id);
+        let (FrontChange::Set(member_refs)
+        | FrontChange::Add(member_refs)
+        | FrontChange::Remove(member_refs)) = &change;
 
-            if !member_id
-                .enabled(&user_state.db)
-                .await
-                .change_context(CommandError::Sqlx)?
+        let mut member_ids = Vec::new();
+
+        for member_ref in member_refs {
+            debug!(requested_member_id = ?member_ref, "Validating member ID");
+            fetch_member!(member_ref, user_state, system_id => member_id);
+
+            if !matches!(change, FrontChange::Remove(_))
+                && !member_id
+                    .enabled(&user_state.db)
+                    .await
+                    .change_context(CommandError::Sqlx)?
             {
                 debug!("Member is disabled");
 
@@ -156,37 +259,262 @@ impl Member {
                 ));
             }
 
-            Some(member_id)
-        };
+            if !member_ids.contains(&member_id) {
+                member_ids.push(member_id);
+            }
+        }
 
-        debug!(target_member_id = ?new_active_member_id, "Changing active member");
+        let mut fronters = system_id
+            .fronting_member_ids(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
 
-        let new_member = system_id
-            .change_fronting_member(new_active_member_id, &user_state.db)
+        match change {
+            FrontChange::Set(_) => fronters = member_ids,
+            FrontChange::Add(_) => {
+                for member_id in member_ids {
+                    if !fronters.contains(&member_id) {
+                        fronters.push(member_id);
+                    }
+                }
+            }
+            FrontChange::Remove(_) => fronters.retain(|id| !member_ids.contains(id)),
+        }
+
+        if duration.is_some_and(|duration| fronters.is_empty() || !duration.is_positive()) {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(
+                    "A timed switch needs someone to switch to and how long for, e.g. `/members switch <member> --for 2h`"
+                        .into(),
+                ),
+            ));
+        }
+
+        // The revert has to be at a date that exists
+        if duration.is_some_and(|duration| {
+            time::OffsetDateTime::now_utc()
+                .checked_add(duration)
+                .is_none()
+        }) {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("That's too long for a timed switch. Use e.g. `--for 2h`".into()),
+            ));
+        }
+
+        debug!(target_member_ids = ?fronters, ?duration, "Changing front");
+
+        if system_id
+            .switch_front(&fronters, switch::Source::Command, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?
+        {
+            announcements::announce_switch(
+                client,
+                system_id,
+                switch::Source::Command,
+                &user_state.db,
+            )
             .await;
+            status::mirror_front(client, system_id, &user_state.db).await;
+        }
+
+        // Scheduled after the switch, as switching cancels any earlier timed switch
+        if let Some(duration) = duration {
+            ScheduledRevert::schedule_timer(system_id, duration, &user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
+        let fronting_members = system_id
+            .fronting_members(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let response = Self::describe_switch(&fronting_members);
+
+        let response = match duration {
+            Some(duration) => format!(
+                "{response}. Switching back to your base account in {}",
+                util::format_duration(duration)
+            ),
+            None => response,
+        };
 
-        let response = match new_member {
-            Ok(Some(member)) => {
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(response),
+        ))
+    }
+
+    /// The response to a switch, from everyone now fronting
+    fn describe_switch(fronting_members: &[models::Member]) -> String {
+        match fronting_members {
+            [] => {
+                info!("Successfully switched to base account");
+                "Switched to base account".into()
+            }
+            [member] => {
                 info!(member_name = %member.full_name, member_id = %member.id, "Successfully switched to member");
                 format!("Switch to member {}", member.full_name)
             }
-            Ok(None) => {
-                info!("Successfully switched to base account");
-                "Switched to base account".into()
+            [primary, co_fronters @ ..] => {
+                info!(primary_member_id = %primary.id, co_fronters = co_fronters.len(), "Successfully switched to co-fronting members");
+                format!(
+                    "Switched to {} (primary), co-fronting with {}",
+                    primary.full_name,
+                    co_fronters
+                        .iter()
+                        .map(|member| member.full_name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
             }
-            Err(e) => return Err(e.change_context(CommandError::Sqlx)),
+        }
+    }
+
+    /// One switch in `/members history`, fronted by `names`
+    fn history_block(
+        switch: &models::Switch,
+        names: &str,
+        now: time::OffsetDateTime,
+    ) -> SlackBlock {
+        let ended_at = switch
+            .ended_at
+            .map_or(now, time::PrimitiveDateTime::assume_utc);
+        let duration = util::format_duration(ended_at - switch.created_at.assume_utc());
+
+        SlackSectionBlock::new()
+            .with_text(md!(
+                "*{}* {}",
+                names,
+                if switch.ended_at.is_some() {
+                    format!("for {duration}")
+                } else {
+                    format!("(fronting for {duration})")
+                }
+            ))
+            .with_fields(vec![
+                md!("{}", util::slack_date(switch.created_at)),
+                md!("Switched by {}", switch.source.to_string().to_lowercase()),
+            ])
+            .into()
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id, member_id))]
+    async fn history(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        since: Option<time::Duration>,
+        member_ref: Option<MemberRef>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Listing switch history");
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let member_id = if let Some(member_ref) = member_ref {
+            fetch_member!(member_ref, user_state, system_id => member_id);
+            Some(member_id)
+        } else {
+            None
         };
 
+        let now = time::OffsetDateTime::now_utc();
+        let since = match since {
+            Some(since) => {
+                let Some(since) = now.checked_sub(since) else {
+                    return Ok(SlackCommandEventResponse::new(
+                        SlackMessageContent::new()
+                            .with_text("That's too far back. Use e.g. `--since 4w`".into()),
+                    ));
+                };
+
+                Some(time::PrimitiveDateTime::new(since.date(), since.time()))
+            }
+            None => None,
+        };
+
+        let switches = models::Switch::fetch_by_system_id(
+            system_id,
+            since,
+            member_id,
+            MAX_HISTORY_SWITCHES + 1,
+            &user_state.db,
+        )
+        .await
+        .change_context(CommandError::Sqlx)?;
+
+        debug!(len = switches.len(), "Fetched switches");
+
+        if switches.is_empty() {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("No switches found.".into()),
+            ));
+        }
+
+        let co_fronters = models::Switch::fetch_co_fronters_by_system_id(system_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let members = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?
+            .members(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+        let member_name = |id: Option<member::Id<_>>| {
+            id.map_or("Base account", |id| {
+                members
+                    .iter()
+                    .find(|member| member.id == id)
+                    .map_or("Unknown member", |member| member.full_name.as_str())
+            })
+        };
+
+        let mut blocks: Vec<SlackBlock> = switches
+            .iter()
+            .take(MAX_HISTORY_SWITCHES as usize)
+            .map(|switch| {
+                let names = std::iter::once(switch.member_id)
+                    .chain(
+                        co_fronters
+                            .get(&switch.id.id)
+                            .into_iter()
+                            .flatten()
+                            .map(|id| Some(*id)),
+                    )
+                    .map(member_name)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                Self::history_block(switch, &names, now)
+            })
+            .collect();
+
+        if switches.len() > MAX_HISTORY_SWITCHES as usize {
+            blocks.push(
+                SlackContextBlock::new(vec![SlackContextBlockElement::MarkDown(
+                    SlackBlockMarkDownText::new(format!(
+                        "Only the latest {MAX_HISTORY_SWITCHES} switches are shown."
+                    )),
+                )])
+                .into(),
+            );
+        }
+
         Ok(SlackCommandEventResponse::new(
-            SlackMessageContent::new().with_text(response),
+            SlackMessageContent::new().with_blocks(blocks),
         ))
     }
 
-    #[tracing::instrument(skip(event, state), fields(user_id, system_id))]
+    #[tracing::instrument(skip(event, state), fields(user_id, system_id, group_id))]
     async fn list_members(
         event: SlackCommandEvent,
         state: SlackClientEventsUserState,
         system: Option<String>,
+        group: Option<GroupRef>,
     ) -> Result<SlackCommandEventResponse, CommandError> {
         trace!("Listing all members");
         let states = state.read().await;
@@ -226,12 +554,51 @@ impl Member {
 
         fields!(system_id = %system.id);
 
-        let member_blocks = sqlx::query!(
-            "
+        if !system.member_list_privacy.is_visible(is_author) {
+            debug!("Target system's member list is private");
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("This system's member list is private.".into()),
+            ));
+        }
+
+        let group_id = match group {
+            Some(group) => {
+                fetch_group!(group, user_state, system.id => group_id);
+                Some(group_id)
+            }
+            None => None,
+        };
+
+        let member_blocks =
+            Self::member_list_blocks(system.id, group_id, is_author, &user_state.db).await?;
+
+        if member_blocks.is_empty() && group_id.is_some() {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("This group has no members.".into()),
+            ));
+        }
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(member_blocks),
+        ))
+    }
+
+    /// One block per member in the system, or in the group if one is given.
+    /// Private members are left out unless the system is the author's
+    async fn member_list_blocks(
+        system_id: system::Id<Trusted>,
+        group_id: Option<group::Id<Trusted>>,
+        is_author: bool,
+        db: &SqlitePool,
+    ) -> Result<Vec<SlackBlock>, CommandError> {
+        sqlx::query!(
+            r#"
                 SELECT
                     members.id,
                     display_name,
-                    full_name,
+                    -- Other users see the display name instead of a private full name
+                    CASE WHEN $3 OR name_privacy = 0 THEN full_name ELSE display_name END as "name!: String",
                     enabled,
                     GROUP_CONCAT(aliases.alias, ', ') as aliases
                 FROM
@@ -239,12 +606,22 @@ impl Member {
                 JOIN
                     aliases ON members.id = aliases.member_id
                 WHERE
-                    members.system_id = $1
+                    members.system_id = $1 AND
+                    ($3 OR members.privacy = 0) AND
+                    (
+                        $2 IS NULL OR
+                        EXISTS (
+                            SELECT 1 FROM group_members
+                            WHERE group_members.group_id = $2 AND group_members.member_id = members.id
+                        )
+                    )
                 GROUP BY members.id
-            ",
-            system.id
+            "#,
+            system_id,
+            group_id,
+            is_author
         )
-        .fetch(&user_state.db)
+        .fetch(db)
         .map_ok(|member| {
             let fields = [
                 Some(md!("*Member ID*: {}", member.id)),
@@ -257,17 +634,13 @@ impl Member {
             .collect();
 
             SlackSectionBlock::new()
-                .with_text(md!("*{}*", member.full_name))
+                .with_text(md!("*{}*", member.name))
                 .with_fields(fields)
         })
         .map_ok(Into::into)
         .map_err(|err| report!(err).change_context(CommandError::Sqlx))
         .try_collect()
-        .await?;
-
-        Ok(SlackCommandEventResponse::new(
-            SlackMessageContent::new().with_blocks(member_blocks),
-        ))
+        .await
     }
 
     #[tracing::instrument(skip(event, state), fields(user_id = %event.user_id, system_id, member_id))]
@@ -295,14 +668,14 @@ impl Member {
             ));
         }
 
-        let system_fronting_member_id = system_id
-            .currently_fronting_member_id(&user_state.db)
+        let fronting_member_ids = system_id
+            .fronting_member_ids(&user_state.db)
             .await
             .change_context(CommandError::Sqlx)?;
 
-        if system_fronting_member_id.is_some_and(|id| id == member_id) {
+        if fronting_member_ids.contains(&member_id) {
             return Ok(SlackCommandEventResponse::new(
-                SlackMessageContent::new().with_text("Cannot disable the currently fronting member. You can use `/members switch` to switch to another member.".into()),
+                SlackMessageContent::new().with_text("Cannot disable a currently fronting member. You can use `/members switch` to switch to another member.".into()),
             ));
         }
 
@@ -351,6 +724,144 @@ impl Member {
         ))
     }
 
+    #[tracing::instrument(skip(event, state), fields(user_id = %event.user_id, system_id, member_id))]
+    async fn system_tag(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        member: MemberRef,
+        show: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running member system tag command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_member!(member, user_state, system_id => member_id);
+
+        member_id
+            .set_show_system_tag(show, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(
+                if show {
+                    "The system tag will be shown after this member's name"
+                } else {
+                    "The system tag won't be shown after this member's name"
+                }
+                .into(),
+            ),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(user_id = %event.user_id, system_id, member_id))]
+    async fn privacy(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        member: MemberRef,
+        changes: member::PrivacyChanges,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running member privacy command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_member!(member, user_state, system_id => member_id);
+
+        let updated = !changes.is_empty();
+
+        if updated {
+            member_id
+                .update_privacy(&changes, &user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
+        let member = member_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let settings = format!(
+            "*Member:* {}\n*Full name:* {}\n*Pronouns:* {}\n*Description:* {}\n*Birthday:* {}\n*Custom fields:* {}",
+            member.privacy,
+            member.name_privacy,
+            member.pronoun_privacy,
+            member.description_privacy,
+            member.birthday_privacy,
+            member.custom_fields_privacy,
+        );
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(if updated {
+                format!("Updated privacy!\n{settings}")
+            } else {
+                settings
+            }),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, client, state), fields(user_id = %event.user_id, system_id, member_id))]
+    async fn request_avatar(
+        event: SlackCommandEvent,
+        client: &SlackHyperClient,
+        state: &SlackClientEventsUserState,
+        member_ref: MemberRef,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Running member avatar command");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        let user_id = event.user_id.clone();
+
+        fetch_system!(event, user_state => system_id);
+
+        fetch_member!(member_ref, user_state, system_id => member_id);
+
+        let member = member_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let session = client.open_session(&BOT_TOKEN);
+
+        let conversation = session
+            .conversations_open(&SlackApiConversationsOpenRequest::new().with_users(vec![user_id]))
+            .await
+            .change_context(CommandError::SlackApi)?
+            .channel;
+
+        let message = session
+            .chat_post_message(&SlackApiChatPostMessageRequest::new(
+                conversation.id,
+                SlackMessageContent::new().with_text(format!(
+                    "Reply to this message with an image to make it {}'s avatar. It can be a PNG, JPEG or GIF up to 2 MB.",
+                    member.display_name
+                )),
+            ))
+            .await
+            .change_context(CommandError::SlackApi)?;
+
+        AvatarRequest::create(&message.channel, &message.ts, member_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        debug!("Sent avatar request");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "Check your DMs to upload {}'s avatar!",
+                member.display_name
+            )),
+        ))
+    }
+
     #[tracing::instrument(skip(event, state), fields(user_id = %event.user_id, system_id, member_id))]
     async fn member_info(
         event: SlackCommandEvent,
@@ -381,12 +892,19 @@ impl Member {
             ));
         }
 
-        let system_fronting_member_id = system_id
-            .currently_fronting_member_id(&user_state.db)
+        let fronting_member_ids = system_id
+            .fronting_member_ids(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let custom_fields = member_id
+            .custom_fields(&user_state.db)
             .await
             .change_context(CommandError::Sqlx)?;
 
-        let blocks = slack_blocks![
+        let profile = member::profile_blocks(&member, &custom_fields);
+
+        let mut blocks = slack_blocks![
             some_into(SlackHeaderBlock::new(member.full_name.into())),
             some_into(SlackDividerBlock::new()),
             some_into(
@@ -407,10 +925,15 @@ impl Member {
                         ))
                     )))
             ),
-            optionally_into(system_fronting_member_id.is_some_and(|id| id == member.id) => SlackSectionBlock::new().with_text(md!("*Fronting*")))
-            // TO-DO: fields
+            optionally_into(fronting_member_ids.first() == Some(&member.id) => SlackSectionBlock::new().with_text(md!("*Fronting*"))),
+            optionally_into(fronting_member_ids.iter().skip(1).any(|id| *id == member.id) => SlackSectionBlock::new().with_text(md!("*Co-fronting*"))),
+            optionally_into(!member.show_system_tag => SlackContextBlock::new(vec![SlackContextBlockElement::MarkDown(
+                SlackBlockMarkDownText::new("The system tag isn't shown after this member's name".into())
+            )]))
         ];
 
+        blocks.extend(profile);
+
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_blocks(blocks),
         ))
@@ -457,7 +980,16 @@ impl Member {
             .await
             .change_context(CommandError::Sqlx)?;
 
-        let view = member::View::from(member).create_edit_view(member_id);
+        let custom_fields = member_id
+            .custom_fields(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let view = member::View {
+            custom_fields,
+            ..member::View::from(member)
+        }
+        .create_edit_view(member_id);
 
         let view = session
             .views_open(&SlackApiViewsOpenRequest::new(
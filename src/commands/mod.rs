@@ -8,6 +8,7 @@
 use std::sync::Arc;
 
 mod alias;
+mod group;
 mod member;
 mod sync;
 mod system;
@@ -17,6 +18,7 @@ use alias::Alias;
 use axum::{Extension, Json};
 use clap::{Parser, error::ErrorKind};
 use error_stack::ResultExt;
+use group::Group;
 use slack_morphism::prelude::*;
 use tracing::{Level, debug, error, trace};
 
@@ -39,6 +41,8 @@ enum Command {
     #[clap(subcommand)]
     Aliases(Alias),
     #[clap(subcommand)]
+    Groups(Group),
+    #[clap(subcommand)]
     Sync(Sync),
     /// Provides an explanation of this bot.
     Explain,
@@ -69,6 +73,10 @@ impl Command {
                 .run(event, state)
                 .await
                 .change_context(CommandError::Aliases),
+            Self::Groups(groups) => groups
+                .run(event, client, state)
+                .await
+                .change_context(CommandError::Groups),
             Self::Sync(sync) => sync
                 .run(event, client, state)
                 .await
@@ -87,7 +95,7 @@ impl Command {
 
                 Due to Slack's limitations, these messages will show up with the [APP] tag - however, they are not apps/bots. You can use message actions to find who the message was sent by.
 
-                If you wish to use the bot yourself, you can start with `/system help` and `/members help`. Other commands: `/triggers help`, `/aliases help`, `/sync help`.
+                If you wish to use the bot yourself, you can start with `/system help` and `/members help`. Other commands: `/triggers help`, `/aliases help`, `/groups help`, `/sync help`.
                 "#}.into(),
             ),
         ).with_response_type(SlackMessageResponseType::InChannel)
@@ -104,6 +112,8 @@ enum CommandError {
     System,
     /// Error running the aliases command
     Aliases,
+    /// Error running the groups command
+    Groups,
     /// Error running the sync command
     Sync,
 }
@@ -81,6 +81,10 @@ impl Sync {
                         title: None,
                         name_pronunciation: None,
                         name_recording_url: None,
+                        description: None,
+                        color: None,
+                        birthday: None,
+                        custom_fields: Vec::new(),
                     }
                     .add(system_id, &user_state.db)
                     .await
@@ -1,16 +1,26 @@
-use crate::fetch_system;
+use crate::{fetch_member, fetch_system};
 use std::sync::Arc;
 
 use error_stack::{Result, ResultExt};
 use oauth2::CsrfToken;
 use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
 use tokio::runtime::Handle;
-use tracing::{debug, trace};
+use tracing::{debug, trace, warn};
 
 use crate::{
     fields,
-    models::{self, user},
+    models::{
+        self,
+        autoproxy::ChannelAutoproxy,
+        member::{self, MemberRef},
+        privacy::Privacy,
+        switch, system,
+        trust::Trusted,
+        user,
+    },
     oauth::create_oauth_client,
+    status, util,
 };
 
 #[derive(clap::Subcommand, Debug)]
@@ -33,6 +43,128 @@ pub enum System {
         /// The user to get info about (if left blank, defaults to you)
         user: Option<String>,
     },
+    /// Shows or changes your system's settings. Run without any options to see the current settings
+    Config {
+        /// Whether a message with triggers on several lines is sent as one message per member
+        #[clap(long)]
+        split_messages: Option<bool>,
+        /// Messages starting with this are never proxied, even with a trigger or a fronting member (e.g. `\`)
+        #[clap(long)]
+        proxy_escape: Option<String>,
+        /// Whether the proxy escape is removed from the message after sending it
+        #[clap(long)]
+        strip_proxy_escape: Option<bool>,
+        /// Revert to your base account after not sending any messages for this long (e.g. `4h`), or `off`
+        #[clap(long, value_name = "DURATION", value_parser = util::parse_timeout)]
+        inactivity_timeout: Option<time::Duration>,
+        /// DM you a reminder to check the front is still right after it stays the same for this long (e.g. `6h`),
+        /// or `off`
+        #[clap(long, value_name = "DURATION", value_parser = util::parse_timeout)]
+        check_in: Option<time::Duration>,
+    },
+    /// Shows or changes how messages without a trigger are proxied. Run without any options to see the current setting
+    ///
+    /// - off: messages without a trigger are sent from your own account
+    /// - front: messages are sent as the primary fronter (see /members switch)
+    /// - latch: messages are sent as whoever last used a trigger, without switching the front
+    /// - member: messages are always sent as the given member
+    ///
+    /// Use `--channel` to give a channel its own setting (e.g. `/system autoproxy off --channel #work`).
+    /// In latch mode, a channel with its own setting keeps its own latched member.
+    ///
+    /// Use `--thread-latch` to have each thread latch onto whoever last used a trigger in it, until nobody has
+    /// posted as them there for a while (e.g. `/system autoproxy --thread-latch 2h`, or `--thread-latch off`).
+    Autoproxy(AutoproxyArgs),
+    /// Shows or changes where switches are announced. Run without any options to see the current setting
+    ///
+    /// Whenever your front changes, "X is now fronting" is posted in the channel under the member's name and profile
+    /// picture (e.g. `/system announce #friends`). For a private channel, invite the bot to it first.
+    Announce {
+        /// The channel to announce switches in
+        #[clap(value_parser = util::parse_slack_channel_id)]
+        channel: Option<SlackChannelId>,
+        /// Stop announcing switches
+        #[clap(long, conflicts_with = "channel")]
+        off: bool,
+        /// Whether switches from triggers are announced too
+        #[clap(long)]
+        trigger_switches: Option<bool>,
+    },
+    /// Shows or changes whether your Slack status shows who's fronting. Run without any options to see the current settings
+    ///
+    /// While anyone is fronting, your status is set to e.g. "Fronting: Alex", and your own status is put back when you
+    /// switch to your base account. This needs permission to edit your profile:
+    /// if your system was set up before this was added, run /system reauth first.
+    Status {
+        /// Whether your status shows who's fronting
+        #[clap(long)]
+        mirror: Option<bool>,
+        /// Whether your display name is changed to the primary fronter's too
+        #[clap(long)]
+        display_name: Option<bool>,
+        /// The status emoji while mirroring (e.g. `:sparkles:`)
+        #[clap(long)]
+        emoji: Option<String>,
+    },
+    /// Shows or changes your system tag, shown after every member's name on their messages (e.g. `/system tag | Sunflower System`).
+    /// Run without a tag to see the current one
+    ///
+    /// Members can opt out with `/members system-tag <member> false`.
+    Tag {
+        /// The tag. Can contain spaces
+        #[clap(num_args = 1.., trailing_var_arg = true)]
+        tag: Vec<String>,
+        /// Remove the tag
+        #[clap(long, conflicts_with = "tag")]
+        clear: bool,
+    },
+    /// Shows or changes who can see your system's front and member list. Run without any options to see the current settings
+    ///
+    /// Both are public by default. Private ones are only shown to you.
+    /// Use `/members privacy` to hide a member or parts of their profile.
+    Privacy {
+        /// Whether other users can see who's fronting, in `/system info` and message info
+        #[clap(long)]
+        front: Option<Privacy>,
+        /// Whether other users can list your members with `/members list`
+        #[clap(long)]
+        member_list: Option<Privacy>,
+    },
+    /// Shows statistics about your system, worked out from your switch history
+    #[clap(subcommand)]
+    Stats(Stats),
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Stats {
+    /// Shows how long each member fronted, their longest stretch and how often they were switched in
+    ///
+    /// Time spent co-fronting counts for every member fronting, so the percentages can add up to more than 100%.
+    Fronting {
+        /// How far back to look (e.g. `7d`, `12w`)
+        #[clap(long, default_value = "30d", value_parser = util::parse_duration)]
+        range: time::Duration,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+pub struct AutoproxyArgs {
+    /// The autoproxy mode
+    mode: Option<system::AutoproxyMode>,
+    /// The member to send messages as. Only used with the `member` mode
+    member: Option<MemberRef>,
+    /// Show or change the setting for this channel only, instead of the whole system
+    #[clap(long, value_parser = util::parse_slack_channel_id)]
+    channel: Option<SlackChannelId>,
+    /// Remove the channel's own setting, so it uses the system's setting again
+    #[clap(long, requires = "channel", conflicts_with = "mode")]
+    reset: bool,
+    /// Whether using a trigger also switches the front to the member the trigger belongs to
+    #[clap(long, conflicts_with = "channel")]
+    switch_on_trigger: Option<bool>,
+    /// How long a thread stays latched onto whoever last used a trigger in it without being used, or `off`
+    #[clap(long, value_name = "DURATION", value_parser = util::parse_timeout, conflicts_with = "channel")]
+    thread_latch: Option<time::Duration>,
 }
 
 #[derive(thiserror::Error, displaydoc::Display, Debug)]
@@ -53,7 +185,762 @@ impl System {
             Self::Create => Self::create_system(event, state).await,
             Self::Info { user } => Self::get_system_info(event, client, state, user).await,
             Self::Reauth => Self::reauth(event, state).await,
+            Self::Config {
+                split_messages,
+                proxy_escape,
+                strip_proxy_escape,
+                inactivity_timeout,
+                check_in,
+            } => {
+                Self::config(
+                    event,
+                    state,
+                    split_messages,
+                    proxy_escape,
+                    strip_proxy_escape,
+                    inactivity_timeout,
+                    check_in,
+                )
+                .await
+            }
+            Self::Autoproxy(args) => Self::autoproxy(event, state, args).await,
+            Self::Announce {
+                channel,
+                off,
+                trigger_switches,
+            } => Self::announce(event, state, channel, off, trigger_switches).await,
+            Self::Status {
+                mirror,
+                display_name,
+                emoji,
+            } => Self::status(event, &client, state, mirror, display_name, emoji).await,
+            Self::Tag { tag, clear } => Self::tag(event, state, tag, clear).await,
+            Self::Privacy { front, member_list } => {
+                Self::privacy(event, state, front, member_list).await
+            }
+            Self::Stats(Stats::Fronting { range }) => {
+                Self::fronting_stats(event, state, range).await
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(event, state))]
+    async fn autoproxy(
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+        args: AutoproxyArgs,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Configuring autoproxy");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let member_id = match (args.mode, args.member) {
+            (Some(system::AutoproxyMode::Member), Some(member_ref)) => {
+                fetch_member!(member_ref, user_state, system_id => member_id);
+
+                if !member_id
+                    .enabled(&user_state.db)
+                    .await
+                    .change_context(CommandError::Sqlx)?
+                {
+                    return Ok(SlackCommandEventResponse::new(
+                        SlackMessageContent::new().with_text(
+                            "That member is disabled! Either re-enable them or choose another member."
+                                .into(),
+                        ),
+                    ));
+                }
+
+                Some(member_id)
+            }
+            (Some(system::AutoproxyMode::Member), None) => {
+                return Ok(SlackCommandEventResponse::new(
+                    SlackMessageContent::new().with_text(
+                        "Give the member to send messages as, e.g. `/system autoproxy member <member>`"
+                            .into(),
+                    ),
+                ));
+            }
+            (_, Some(_)) => {
+                return Ok(SlackCommandEventResponse::new(
+                    SlackMessageContent::new().with_text(
+                        "A member can only be given with the `member` mode, e.g. `/system autoproxy member <member>`"
+                            .into(),
+                    ),
+                ));
+            }
+            (_, None) => None,
+        };
+
+        if let Some(channel_id) = args.channel {
+            return Self::channel_autoproxy(
+                system_id,
+                &channel_id,
+                args.mode.map(|mode| (mode, member_id)),
+                args.reset,
+                &user_state.db,
+            )
+            .await;
+        }
+
+        Self::system_autoproxy(
+            system_id,
+            args.mode.map(|mode| (mode, member_id)),
+            args.switch_on_trigger,
+            args.thread_latch,
+            &user_state.db,
+        )
+        .await
+    }
+
+    /// Shows or changes the system-wide autoproxy setting, along with every channel's own setting.
+    ///
+    /// `change` is the new mode and member (for [`system::AutoproxyMode::Member`]), if any.
+    /// `thread_latch` is the new thread latch timeout, where zero turns thread latching off.
+    async fn system_autoproxy(
+        system_id: system::Id<Trusted>,
+        change: Option<(system::AutoproxyMode, Option<member::Id<Trusted>>)>,
+        switch_on_trigger: Option<bool>,
+        thread_latch: Option<time::Duration>,
+        db: &SqlitePool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        let changed = change.is_some() || switch_on_trigger.is_some() || thread_latch.is_some();
+
+        if let Some(timeout) = thread_latch {
+            system_id
+                .set_thread_latch_timeout(Some(timeout).filter(|timeout| timeout.is_positive()), db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
+        if change.is_some() || switch_on_trigger.is_some() {
+            system_id
+                .update_autoproxy(
+                    change.map(|(mode, _)| mode),
+                    change.and_then(|(_, member_id)| member_id),
+                    switch_on_trigger,
+                    db,
+                )
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
+        let system = system_id
+            .fetch(db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let mut settings = vec![
+            format!(
+                "*Autoproxy:* {}",
+                Self::describe_autoproxy(system.autoproxy_mode, system.autoproxy_member_id(), db)
+                    .await?
+            ),
+            format!(
+                "*Triggers switch the front:* {}",
+                if system.auto_switch_on_trigger {
+                    "on"
+                } else {
+                    "off"
+                }
+            ),
+            format!(
+                "*Thread latch:* {}",
+                system.thread_latch_timeout().map_or_else(
+                    || "off".to_string(),
+                    |timeout| format!("on, for {} without use", util::format_duration(timeout))
+                )
+            ),
+        ];
+
+        for channel_autoproxy in ChannelAutoproxy::fetch_by_system_id(system_id, db)
+            .await
+            .change_context(CommandError::Sqlx)?
+        {
+            settings.push(format!(
+                "*In <#{}>:* {}",
+                channel_autoproxy.channel_id,
+                Self::describe_autoproxy(
+                    channel_autoproxy.mode,
+                    channel_autoproxy.member_id(&system),
+                    db
+                )
+                .await?
+            ));
+        }
+
+        let settings = settings.join("\n");
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(if changed {
+                format!("Updated autoproxy!\n{settings}")
+            } else {
+                settings
+            }),
+        ))
+    }
+
+    /// Shows or changes a channel's own autoproxy setting.
+    ///
+    /// `change` is the new mode and member (for [`system::AutoproxyMode::Member`]), if any.
+    async fn channel_autoproxy(
+        system_id: system::Id<Trusted>,
+        channel_id: &SlackChannelId,
+        change: Option<(system::AutoproxyMode, Option<member::Id<Trusted>>)>,
+        reset: bool,
+        db: &SqlitePool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        fields!(channel_id = %channel_id);
+
+        if reset {
+            let removed = ChannelAutoproxy::delete(system_id, channel_id, db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(if removed {
+                    format!("<#{channel_id}> uses your system's autoproxy setting again.")
+                } else {
+                    format!("<#{channel_id}> already uses your system's autoproxy setting.")
+                }),
+            ));
+        }
+
+        if let Some((mode, member_id)) = change {
+            ChannelAutoproxy::upsert(system_id, channel_id, mode, member_id, db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
+        let Some(channel_autoproxy) = ChannelAutoproxy::fetch(system_id, channel_id, db)
+            .await
+            .change_context(CommandError::Sqlx)?
+        else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(format!(
+                    "<#{channel_id}> uses your system's autoproxy setting. Give a mode to change it just for this channel."
+                )),
+            ));
+        };
+
+        let system = system_id
+            .fetch(db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let settings = format!(
+            "*Autoproxy in <#{channel_id}>:* {}",
+            Self::describe_autoproxy(
+                channel_autoproxy.mode,
+                channel_autoproxy.member_id(&system),
+                db
+            )
+            .await?
+        );
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(if change.is_some() {
+                format!("Updated autoproxy!\n{settings}")
+            } else {
+                settings
+            }),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state))]
+    async fn announce(
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+        channel: Option<SlackChannelId>,
+        off: bool,
+        trigger_switches: Option<bool>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Configuring switch announcements");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let changed = channel.is_some() || off || trigger_switches.is_some();
+
+        if channel.is_some() || off {
+            system_id
+                .set_announcement_channel(channel.as_ref(), &user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
+        if let Some(trigger_switches) = trigger_switches {
+            system_id
+                .set_announce_trigger_switches(trigger_switches, &user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
+        let system = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let settings = format!(
+            "*Switch announcements:* {}\n*Switches from triggers:* {}",
+            system.announcement_channel().map_or_else(
+                || "off".to_string(),
+                |channel_id| format!("in <#{channel_id}>")
+            ),
+            if system.announce_trigger_switches {
+                "announced"
+            } else {
+                "not announced"
+            }
+        );
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(if changed {
+                format!("Updated switch announcements!\n{settings}")
+            } else {
+                settings
+            }),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state))]
+    async fn tag(
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+        tag: Vec<String>,
+        clear: bool,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Configuring system tag");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        if clear {
+            system_id
+                .set_tag(None, &user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("Removed your system tag.".into()),
+            ));
+        }
+
+        if tag.is_empty() {
+            let system = system_id
+                .fetch(&user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(system.tag.map_or_else(
+                    || "You don't have a system tag. Set one with `/system tag <tag>`".to_string(),
+                    |tag| format!("Your system tag is `{}`", util::escape(&tag)),
+                )),
+            ));
+        }
+
+        let tag = util::unescape(&tag.join(" "));
+
+        if tag.chars().count() > models::system::MAX_TAG_LENGTH {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(format!(
+                    "System tags can be at most {} characters long, so there's room for members' names.",
+                    models::system::MAX_TAG_LENGTH
+                )),
+            ));
+        }
+
+        system_id
+            .set_tag(Some(&tag), &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "Updated your system tag to `{}`",
+                util::escape(&tag)
+            )),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state))]
+    async fn privacy(
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+        front: Option<Privacy>,
+        member_list: Option<Privacy>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Configuring system privacy");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let changed = front.is_some() || member_list.is_some();
+
+        if changed {
+            system_id
+                .update_privacy(front, member_list, &user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
+        let system = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let settings = format!(
+            "*Front:* {}\n*Member list:* {}",
+            system.front_privacy, system.member_list_privacy
+        );
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(if changed {
+                format!("Updated privacy!\n{settings}")
+            } else {
+                settings
+            }),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, client, state))]
+    async fn status(
+        event: SlackCommandEvent,
+        client: &SlackHyperClient,
+        state: SlackClientEventsUserState,
+        mirror: Option<bool>,
+        display_name: Option<bool>,
+        emoji: Option<String>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Configuring status mirroring");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        if emoji.as_ref().is_some_and(|emoji| {
+            emoji.len() < 3 || !emoji.starts_with(':') || !emoji.ends_with(':')
+        }) {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("Give the emoji by its name, e.g. `:sparkles:`".into()),
+            ));
+        }
+
+        let changed = mirror.is_some() || display_name.is_some() || emoji.is_some();
+        let mut warning = None;
+
+        if changed {
+            let system = system_id
+                .fetch(&user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            // Put the owner's own status back first, so it's mirrored again from scratch with the new settings
+            let result = status::restore(client, &system, &user_state.db).await;
+
+            system_id
+                .update_status_mirroring(mirror, display_name, emoji, &user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+
+            let result = match result {
+                Ok(()) => status::try_mirror_front(client, system_id, &user_state.db).await,
+                Err(error) => Err(error),
+            };
+
+            match result {
+                Ok(()) => {}
+                Err(error) if matches!(error.current_context(), status::StatusError::Slack) => {
+                    warn!(?error, "Failed to update Slack profile");
+                    warning = Some(
+                        "Couldn't update your Slack status. If your system was set up before status mirroring was added, run /system reauth to let the bot edit your profile.",
+                    );
+                }
+                Err(error) => return Err(error.change_context(CommandError::Sqlx)),
+            }
+        }
+
+        let system = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let settings = format!(
+            "*Status mirroring:* {}\n*Display name:* {}\n*Status emoji:* {}",
+            if system.mirror_status { "on" } else { "off" },
+            if system.mirror_display_name {
+                "mirrored"
+            } else {
+                "not mirrored"
+            },
+            system.status_emoji
+        );
+
+        let text = match (changed, warning) {
+            (true, Some(warning)) => {
+                format!("Updated status mirroring, but: {warning}\n{settings}")
+            }
+            (true, None) => format!("Updated status mirroring!\n{settings}"),
+            (false, _) => settings,
+        };
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(text),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state))]
+    async fn fronting_stats(
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+        range: time::Duration,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Calculating fronting stats");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        if !range.is_positive() {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("Give a range longer than zero, e.g. `--range 30d`".into()),
+            ));
+        }
+
+        let end = time::OffsetDateTime::now_utc();
+        let Some(start) = end.checked_sub(range) else {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("That range is too long. Use e.g. `--range 52w`".into()),
+            ));
+        };
+
+        let switches = models::Switch::fetch_overlapping(
+            system_id,
+            time::PrimitiveDateTime::new(start.date(), start.time()),
+            &user_state.db,
+        )
+        .await
+        .change_context(CommandError::Sqlx)?;
+        let co_fronters = models::Switch::fetch_co_fronters_by_system_id(system_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let member_stats = switch::front_stats(&switches, &co_fronters, start, end);
+        debug!(
+            switches = switches.len(),
+            members = member_stats.len(),
+            "Calculated fronting stats"
+        );
+
+        let range_text = util::format_duration(range);
+
+        if member_stats.is_empty() {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text(format!("Nobody fronted in the last {range_text}.")),
+            ));
         }
+
+        let members = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?
+            .members(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let member_blocks = member_stats.iter().map(|stats| {
+            let name = members
+                .iter()
+                .find(|member| member.id == stats.member_id)
+                .map_or("Unknown member", |member| member.full_name.as_str());
+
+            SlackSectionBlock::new()
+                .with_text(md!("*{}*", name))
+                .with_fields(vec![
+                    md!("Fronting time: {}", util::format_duration(stats.total)),
+                    md!(
+                        "% of range: {:.1}%",
+                        stats.total.as_seconds_f64() / range.as_seconds_f64() * 100.0
+                    ),
+                    md!("Longest stretch: {}", util::format_duration(stats.longest)),
+                    md!("Switches in: {}", stats.switches),
+                ])
+                .into()
+        });
+
+        let mut blocks: Vec<SlackBlock> = vec![
+            SlackSectionBlock::new()
+                .with_text(md!("*Fronting over the last {}*", range_text))
+                .into(),
+        ];
+        blocks.extend(member_blocks);
+        blocks.push(
+            SlackContextBlock::new(vec![SlackContextBlockElement::MarkDown(
+                SlackBlockMarkDownText::new(
+                    "Time spent co-fronting counts for every member fronting, so percentages can add up to more than 100%."
+                        .into(),
+                ),
+            )])
+            .into(),
+        );
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new()
+                .with_text(format!("Fronting over the last {range_text}"))
+                .with_blocks(blocks),
+        ))
+    }
+
+    /// Describes an autoproxy mode, along with who it currently sends messages as
+    async fn describe_autoproxy(
+        mode: system::AutoproxyMode,
+        member_id: Option<member::Id<Trusted>>,
+        db: &SqlitePool,
+    ) -> Result<String, CommandError> {
+        let member_name = match member_id {
+            Some(member_id) => Some(
+                member_id
+                    .fetch(db)
+                    .await
+                    .change_context(CommandError::Sqlx)?
+                    .display_name,
+            ),
+            None => None,
+        };
+
+        Ok(match (mode, member_name) {
+            (system::AutoproxyMode::Off, _) => "off".to_string(),
+            (system::AutoproxyMode::Front, Some(name)) => format!("front (currently {name})"),
+            (system::AutoproxyMode::Front, None) => "front (currently nobody)".to_string(),
+            (system::AutoproxyMode::Latch, Some(name)) => format!("latch (currently {name})"),
+            (system::AutoproxyMode::Latch, None) => {
+                "latch (nobody has used a trigger yet)".to_string()
+            }
+            (system::AutoproxyMode::Member, name) => {
+                format!("member ({})", name.as_deref().unwrap_or("unknown member"))
+            }
+        })
+    }
+
+    #[tracing::instrument(skip(event, state))]
+    async fn config(
+        event: SlackCommandEvent,
+        state: SlackClientEventsUserState,
+        split_messages: Option<bool>,
+        proxy_escape: Option<String>,
+        strip_proxy_escape: Option<bool>,
+        inactivity_timeout: Option<time::Duration>,
+        check_in: Option<time::Duration>,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        trace!("Configuring system");
+
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        // An empty escape would match every message, so nothing would ever be proxied
+        if proxy_escape
+            .as_deref()
+            .is_some_and(|escape| util::unescape(escape).trim().is_empty())
+        {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new()
+                    .with_text("The proxy escape can't be empty or only whitespace.".into()),
+            ));
+        }
+
+        let changed = split_messages.is_some()
+            || proxy_escape.is_some()
+            || strip_proxy_escape.is_some()
+            || inactivity_timeout.is_some()
+            || check_in.is_some();
+
+        if let Some(timeout) = inactivity_timeout {
+            system_id
+                .set_inactivity_timeout(
+                    Some(timeout).filter(|timeout| timeout.is_positive()),
+                    &user_state.db,
+                )
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
+        if let Some(interval) = check_in {
+            system_id
+                .set_check_in_interval(
+                    Some(interval).filter(|interval| interval.is_positive()),
+                    &user_state.db,
+                )
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
+        if split_messages.is_some() || proxy_escape.is_some() || strip_proxy_escape.is_some() {
+            system_id
+                .update_settings(
+                    split_messages,
+                    proxy_escape.as_deref().map(util::unescape),
+                    strip_proxy_escape,
+                    &user_state.db,
+                )
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
+        let system = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let settings = format!(
+            "*Split messages:* {}\n*Proxy escape:* `{}` ({})\n*Inactivity timeout:* {}\n*Check-ins:* {}",
+            if system.split_messages { "on" } else { "off" },
+            util::escape(&system.proxy_escape),
+            if system.strip_proxy_escape {
+                "removed from messages"
+            } else {
+                "kept in messages"
+            },
+            system.inactivity_timeout().map_or_else(
+                || "off".to_string(),
+                |timeout| format!(
+                    "back to your base account after {}",
+                    util::format_duration(timeout)
+                )
+            ),
+            system.check_in_interval().map_or_else(
+                || "off".to_string(),
+                |interval| format!(
+                    "after the front stays the same for {}",
+                    util::format_duration(interval)
+                )
+            )
+        );
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(if changed {
+                format!("Updated settings!\n{settings}")
+            } else {
+                settings
+            }),
+        ))
     }
 
     async fn reauth(
@@ -76,7 +963,10 @@ impl System {
             .authorize_url(CsrfToken::new_random)
             // So we get a regular token as well. Required by oauth2 for some reason
             .add_extra_param("scope", "commands")
-            .add_extra_param("user_scope", "users.profile:read,chat:write")
+            .add_extra_param(
+                "user_scope",
+                "users.profile:read,users.profile:write,chat:write",
+            )
             .url();
 
         let secret = csrf_token.secret();
@@ -140,17 +1030,33 @@ impl System {
         if let Some(system) = system {
             fields!(system_id = %system.id);
             debug!("Fetched system");
-            let fronting_member = system
-                .active_member(&user_state.db)
-                .await
-                .change_context(CommandError::Sqlx)?;
+            let is_owner = *system.owner_id == event.user_id;
+
+            let front = if system.front_privacy.is_visible(is_owner) {
+                let mut fronting_members = system
+                    .id
+                    .fronting_members(&user_state.db)
+                    .await
+                    .change_context(CommandError::Sqlx)?;
+
+                // Private members aren't shown as fronting either, like in the member list
+                fronting_members.retain(|member| member.privacy.is_visible(is_owner));
+
+                member::describe_front(&fronting_members)
+                    .unwrap_or_else(|| "No fronting member".to_string())
+            } else {
+                "Private".to_string()
+            };
 
             Ok(SlackCommandEventResponse::new(
                 SlackMessageContent::new().with_blocks(slack_blocks![some_into(
                     SlackSectionBlock::new().with_text(md!(format!(
-                        "Fronting member: {}",
-                        fronting_member
-                            .map_or_else(|| "No fronting member".to_string(), |m| m.display_name)
+                        "Fronting: {}{}",
+                        front,
+                        system
+                            .tag
+                            .map(|tag| format!("\nTag: `{}`", util::escape(&tag)))
+                            .unwrap_or_default()
                     )))
                 )]),
             ))
@@ -196,7 +1102,10 @@ impl System {
             .authorize_url(CsrfToken::new_random)
             // So we get a regular token as well. Required by oauth2 for some reason
             .add_extra_param("scope", "commands")
-            .add_extra_param("user_scope", "users.profile:read,chat:write")
+            .add_extra_param(
+                "user_scope",
+                "users.profile:read,users.profile:write,chat:write",
+            )
             .url();
 
         let secret = csrf_token.secret();
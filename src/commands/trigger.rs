@@ -1,17 +1,28 @@
 use error_stack::{Result, ResultExt};
 use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
 use tracing::debug;
 
 use crate::{
     fetch_member, fetch_system, fields,
-    models::{self, member::MemberRef, trigger, trust::Untrusted, user},
+    models::{
+        self,
+        matcher::Matcher,
+        member::{self, MemberRef},
+        system, trigger,
+        trust::{Trusted, Untrusted},
+        user,
+    },
+    util,
 };
 
 #[derive(clap::Subcommand, Debug)]
 #[clap(verbatim_doc_comment)]
 /// A trigger is a special word/phrase that can be used to send a message under a specific members profile
 ///
-/// A trigger can be a prefix to a message or suffix. The prefix/suffix is erased when resending the message.
+/// A trigger can be a prefix to a message, a suffix, a pair of both, or a regex. The prefix/suffix is erased when resending the message.
+/// A paired trigger uses `text` to mark where the message content goes (e.g. `[text]`), so it can't contain `text` anywhere else.
+/// A regex trigger needs a capture group named `text`, which is used as the message content (e.g. `^J:\s*(?<text>.*)$`).
 ///
 /// Also see:
 /// - /members to manage the members themselves
@@ -23,8 +34,19 @@ pub enum Trigger {
         /// The type of trigger
         #[clap(name = "type")]
         typ: trigger::Type,
-        /// The trigger content
+        /// The trigger content. For paired triggers, use `text` once to mark the message (e.g. `[text]`). For regex triggers, this is the regex itself
         content: String,
+        /// Match the trigger regardless of letter case (e.g. `j:` also matches `J:`)
+        #[clap(long)]
+        case_insensitive: bool,
+        /// Ignore whitespace around the trigger (e.g. `J:` also matches ` J:hello`)
+        #[clap(long)]
+        ignore_whitespace: bool,
+        #[clap(flatten)]
+        channels: ChannelArgs,
+        /// Add the trigger even if it overlaps with another member's trigger
+        #[clap(long)]
+        force: bool,
     },
     /// Deletes a trigger
     Delete {
@@ -46,9 +68,91 @@ pub enum Trigger {
         /// The trigger content
         #[clap(long, short)]
         content: Option<String>,
+        #[clap(flatten)]
+        options: EditMatchOptions,
+        #[clap(flatten)]
+        channels: ChannelArgs,
+        /// Save the trigger even if it overlaps with another member's trigger
+        #[clap(long)]
+        force: bool,
     },
+    /// Lists every pair of triggers that overlap, where a message meant for one member could go to another
+    Lint,
+    /// Shows which member a message would be sent as, without sending anything
+    Test {
+        /// The message to test. Spaces between words are collapsed into one
+        #[clap(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        message: Vec<String>,
+    },
+}
+
+/// Changes to a trigger's [`trigger::MatchOptions`]
+#[derive(clap::Args, Debug, Clone, Copy)]
+pub struct EditMatchOptions {
+    /// Whether letter case has to match
+    #[clap(long)]
+    case_sensitive: Option<bool>,
+    /// Whether whitespace around the trigger is ignored
+    #[clap(long)]
+    ignore_whitespace: Option<bool>,
+}
+
+/// Changes to the channels a trigger can be used in
+#[derive(clap::Args, Debug, Clone)]
+pub struct ChannelArgs {
+    /// A channel the trigger is limited to (or excluded from, with `--channel-scope except`).
+    /// Can be given more than once, and replaces the trigger's current channels
+    #[clap(long = "channel", value_parser = util::parse_slack_channel_id)]
+    channels: Vec<SlackChannelId>,
+    /// Whether the trigger is used everywhere, only in the given channels, or everywhere except them.
+    /// Defaults to `only` when channels are given
+    #[clap(long)]
+    channel_scope: Option<trigger::ChannelScope>,
 }
 
+impl ChannelArgs {
+    /// Applies the changes to a trigger's current channel filter.
+    ///
+    /// Returns `None` if nothing changes, or an explanation if the changes don't make sense.
+    fn apply(
+        self,
+        current: &trigger::ChannelFilter,
+    ) -> std::result::Result<Option<trigger::ChannelFilter>, &'static str> {
+        let scope = match (self.channel_scope, self.channels.is_empty()) {
+            (None, true) => return Ok(None),
+            (Some(trigger::ChannelScope::Everywhere), true) => {
+                return Ok(Some(trigger::ChannelFilter::default()));
+            }
+            (Some(trigger::ChannelScope::Everywhere), false) => {
+                return Err(
+                    "A trigger used everywhere can't have channels. Use `--channel-scope only` or `--channel-scope except`",
+                );
+            }
+            (Some(scope), _) => scope,
+            (None, false) if current.scope == trigger::ChannelScope::Everywhere => {
+                trigger::ChannelScope::Only
+            }
+            (None, false) => current.scope,
+        };
+
+        let channels = if self.channels.is_empty() {
+            current.channels.clone()
+        } else {
+            self.channels
+        };
+
+        if channels.is_empty() {
+            return Err("Give the channels the scope applies to with `--channel #channel`");
+        }
+
+        Ok(Some(trigger::ChannelFilter { scope, channels }))
+    }
+}
+
+/// How overlapping triggers are resolved, for messages about conflicts
+const CONFLICT_EXPLANATION: &str =
+    "When a message matches more than one trigger, the one that matches more of the message wins.";
+
 #[derive(thiserror::Error, displaydoc::Display, Debug)]
 pub enum CommandError {
     /// Error while calling the database
@@ -67,12 +171,46 @@ impl Trigger {
                 member,
                 typ,
                 content,
-            } => Self::create_trigger(event, &state, member, typ, content).await,
+                case_insensitive,
+                ignore_whitespace,
+                channels,
+                force,
+            } => {
+                let options = trigger::MatchOptions {
+                    case_sensitive: !case_insensitive,
+                    ignore_whitespace,
+                };
+
+                let channels = match channels.apply(&trigger::ChannelFilter::default()) {
+                    Ok(channels) => channels.unwrap_or_default(),
+                    Err(error) => return Ok(Self::invalid_channels_response(error)),
+                };
+
+                Self::create_trigger(
+                    event,
+                    &state,
+                    member,
+                    (typ, content, options),
+                    channels,
+                    force,
+                )
+                .await
+            }
             Self::Delete { id } => Self::delete_trigger(event, &state, id).await,
             Self::List { member } => Self::list_triggers(event, &state, member).await,
-            Self::Edit { id, typ, content } => {
-                Self::edit_trigger(event, &state, id, typ, content).await
+            Self::Edit {
+                id,
+                typ,
+                content,
+                options,
+                channels,
+                force,
+            } => {
+                Self::edit_trigger(event, &state, id, (typ, content, options), channels, force)
+                    .await
             }
+            Self::Lint => Self::lint_triggers(event, &state).await,
+            Self::Test { message } => Self::test_triggers(event, &state, &message.join(" ")).await,
         }
     }
 
@@ -81,8 +219,9 @@ impl Trigger {
         event: SlackCommandEvent,
         state: &SlackClientEventsUserState,
         member_id: MemberRef,
-        typ: trigger::Type,
-        content: String,
+        (typ, content, options): (trigger::Type, String, trigger::MatchOptions),
+        channels: trigger::ChannelFilter,
+        force: bool,
     ) -> Result<SlackCommandEventResponse, CommandError> {
         let states = state.read().await;
         let user_state = states.get_user_state::<user::State>().unwrap();
@@ -90,9 +229,35 @@ impl Trigger {
         fetch_system!(event, user_state => system_id);
         fetch_member!(member_id, user_state, system_id => member_id);
 
-        models::Trigger::insert(member_id, system_id, typ, content, &user_state.db)
-            .await
-            .change_context(CommandError::Sqlx)?;
+        if let Err(error) = typ.validate(&util::unescape(&content)) {
+            debug!(?error, "Invalid trigger");
+            return Ok(Self::invalid_trigger_response(&error));
+        }
+
+        if !force
+            && let Some(response) = Self::check_conflicts(
+                system_id,
+                member_id,
+                (typ, &content, options, &channels),
+                None,
+                &user_state.db,
+            )
+            .await?
+        {
+            return Ok(response);
+        }
+
+        models::Trigger::insert(
+            member_id,
+            system_id,
+            typ,
+            content,
+            options,
+            &channels,
+            &user_state.db,
+        )
+        .await
+        .change_context(CommandError::Sqlx)?;
 
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_text("Trigger created!".into()),
@@ -179,14 +344,41 @@ impl Trigger {
 
         debug!(len = triggers.len(), "Found triggers");
 
+        let channels = models::Trigger::fetch_channels_by_system_id(system_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
         let trigger_blocks = triggers
             .into_iter()
             .map(|trigger| {
-                let fields = vec![
+                let mut fields = vec![
                     md!("Member ID: {}", trigger.member_id),
                     md!("{}: {}", trigger.typ, trigger.text),
                 ];
 
+                if !trigger.case_sensitive {
+                    fields.push(md!("Case-insensitive"));
+                }
+
+                if trigger.ignore_whitespace {
+                    fields.push(md!("Ignores whitespace"));
+                }
+
+                let filter = trigger.channel_filter(&channels);
+
+                if filter.scope != trigger::ChannelScope::Everywhere {
+                    fields.push(md!(
+                        "{} {}",
+                        filter.scope,
+                        filter
+                            .channels
+                            .iter()
+                            .map(|channel| format!("<#{channel}>"))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ));
+                }
+
                 SlackSectionBlock::new()
                     .with_text(md!("*Trigger {}*", trigger.id))
                     .with_fields(fields)
@@ -204,8 +396,9 @@ impl Trigger {
         event: SlackCommandEvent,
         state: &SlackClientEventsUserState,
         trigger_id: trigger::Id<Untrusted>,
-        typ: Option<trigger::Type>,
-        text: Option<String>,
+        (typ, text, options): (Option<trigger::Type>, Option<String>, EditMatchOptions),
+        channels: ChannelArgs,
+        force: bool,
     ) -> Result<SlackCommandEventResponse, CommandError> {
         let states = state.read().await;
         let user_state = states.get_user_state::<user::State>().unwrap();
@@ -225,13 +418,319 @@ impl Trigger {
 
         fields!(trigger_id = %trigger_id);
 
+        let trigger = trigger_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let current_channels = trigger::ChannelFilter {
+            scope: trigger.channel_scope,
+            channels: trigger_id
+                .channels(&user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?,
+        };
+
+        let new_channels = match channels.apply(&current_channels) {
+            Ok(new_channels) => new_channels,
+            Err(error) => return Ok(Self::invalid_channels_response(error)),
+        };
+
+        if typ.is_some()
+            || text.is_some()
+            || options.case_sensitive.is_some()
+            || options.ignore_whitespace.is_some()
+            || new_channels.is_some()
+        {
+            let new_typ = typ.unwrap_or(trigger.typ);
+            let new_text = text.as_deref().unwrap_or(&trigger.text);
+            let new_options = trigger::MatchOptions {
+                case_sensitive: options.case_sensitive.unwrap_or(trigger.case_sensitive),
+                ignore_whitespace: options
+                    .ignore_whitespace
+                    .unwrap_or(trigger.ignore_whitespace),
+            };
+
+            if let Err(error) = new_typ.validate(&util::unescape(new_text)) {
+                debug!(?error, "Invalid trigger");
+                return Ok(Self::invalid_trigger_response(&error));
+            }
+
+            if !force
+                && let Some(response) = Self::check_conflicts(
+                    system_id,
+                    trigger.member_id,
+                    (
+                        new_typ,
+                        new_text,
+                        new_options,
+                        new_channels.as_ref().unwrap_or(&current_channels),
+                    ),
+                    Some(trigger_id),
+                    &user_state.db,
+                )
+                .await?
+            {
+                return Ok(response);
+            }
+        }
+
         trigger_id
-            .update(typ, text, &user_state.db)
+            .update(
+                typ,
+                text,
+                options.case_sensitive,
+                options.ignore_whitespace,
+                &user_state.db,
+            )
             .await
             .change_context(CommandError::Sqlx)?;
 
+        if let Some(new_channels) = new_channels {
+            trigger_id
+                .set_channel_filter(&new_channels, &user_state.db)
+                .await
+                .change_context(CommandError::Sqlx)?;
+        }
+
         Ok(SlackCommandEventResponse::new(
             SlackMessageContent::new().with_text("Updated trigger!".into()),
         ))
     }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn test_triggers(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+        message: &str,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let system = system_id
+            .fetch(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let message = util::unescape(message);
+
+        if message.starts_with(&system.proxy_escape) {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text(format!(
+                    "That message starts with your proxy escape (`{}`), so it wouldn't be proxied.",
+                    util::escape(&system.proxy_escape)
+                )),
+            ));
+        }
+
+        let matcher = Matcher::fetch(system_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+        let triggered = matcher.find_all(&message, &event.channel_id);
+
+        let Some((best, others)) = triggered.split_first() else {
+            debug!("No triggers matched");
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("No triggers match that message.".into()),
+            ));
+        };
+
+        debug!(trigger_id = %best.trigger_id, others = others.len(), "Triggers matched");
+
+        let members = system
+            .members(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+        let member_name = |id| {
+            members
+                .iter()
+                .find(|member| member.id == id)
+                .map_or("Unknown member", |member| member.display_name.as_str())
+        };
+
+        let mut blocks = vec![
+            SlackSectionBlock::new()
+                .with_text(md!(
+                    "*Would be sent as {}* (member {})",
+                    member_name(best.member_id),
+                    best.member_id
+                ))
+                .with_fields(vec![
+                    md!("Trigger {}", best.trigger_id),
+                    md!("{}: {}", best.typ, best.trigger_text),
+                ])
+                .into(),
+            SlackSectionBlock::new()
+                .with_text(md!(
+                    "Message: {}",
+                    util::escape(&message[best.content.clone()])
+                ))
+                .into(),
+        ];
+
+        if !others.is_empty() {
+            let others = others
+                .iter()
+                .map(|other| {
+                    format!(
+                        "- Trigger {} ({}: {}) for {} (member {})",
+                        other.trigger_id,
+                        other.typ,
+                        other.trigger_text,
+                        member_name(other.member_id),
+                        other.member_id
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            blocks.push(
+                SlackSectionBlock::new()
+                    .with_text(md!("*Also matched:*\n{}", others))
+                    .into(),
+            );
+        }
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_blocks(blocks),
+        ))
+    }
+
+    #[tracing::instrument(skip(event, state), fields(system_id))]
+    async fn lint_triggers(
+        event: SlackCommandEvent,
+        state: &SlackClientEventsUserState,
+    ) -> Result<SlackCommandEventResponse, CommandError> {
+        let states = state.read().await;
+        let user_state = states.get_user_state::<user::State>().unwrap();
+
+        fetch_system!(event, user_state => system_id);
+
+        let triggers = system_id
+            .list_triggers(&user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let channels = models::Trigger::fetch_channels_by_system_id(system_id, &user_state.db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+        let filters = triggers
+            .iter()
+            .map(|trigger| trigger.channel_filter(&channels))
+            .collect::<Vec<_>>();
+
+        let conflicts = triggers
+            .iter()
+            .enumerate()
+            .flat_map(|(i, trigger)| {
+                let filters = &filters;
+
+                triggers
+                    .iter()
+                    .enumerate()
+                    .skip(i + 1)
+                    .filter(move |&(j, other)| {
+                        other.member_id != trigger.member_id
+                            && filters[i].overlaps(&filters[j])
+                            && trigger.overlaps(other.typ, &other.text, other.options())
+                    })
+                    .map(move |(_, other)| {
+                        format!(
+                            "- {} overlaps with {}",
+                            Self::describe(trigger),
+                            Self::describe(other)
+                        )
+                    })
+            })
+            .collect::<Vec<_>>();
+
+        debug!(len = conflicts.len(), "Found conflicts");
+
+        if conflicts.is_empty() {
+            return Ok(SlackCommandEventResponse::new(
+                SlackMessageContent::new().with_text("No overlapping triggers found.".into()),
+            ));
+        }
+
+        Ok(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "Found {} overlapping trigger pair(s):\n{}\n{}",
+                conflicts.len(),
+                conflicts.join("\n"),
+                CONFLICT_EXPLANATION
+            )),
+        ))
+    }
+
+    /// Checks a new or edited trigger against the triggers of the system's other members.
+    ///
+    /// Returns a response explaining any overlaps, or `None` if there aren't any.
+    async fn check_conflicts(
+        system_id: system::Id<Trusted>,
+        member_id: member::Id<Trusted>,
+        (typ, text, options, channels): (
+            trigger::Type,
+            &str,
+            trigger::MatchOptions,
+            &trigger::ChannelFilter,
+        ),
+        editing: Option<trigger::Id<Trusted>>,
+        db: &SqlitePool,
+    ) -> Result<Option<SlackCommandEventResponse>, CommandError> {
+        let channel_filters = models::Trigger::fetch_channels_by_system_id(system_id, db)
+            .await
+            .change_context(CommandError::Sqlx)?;
+
+        let conflicts = system_id
+            .list_triggers(db)
+            .await
+            .change_context(CommandError::Sqlx)?
+            .into_iter()
+            .filter(|trigger| {
+                Some(trigger.id) != editing
+                    && trigger.member_id != member_id
+                    && trigger.channel_filter(&channel_filters).overlaps(channels)
+                    && trigger.overlaps(typ, text, options)
+            })
+            .map(|trigger| format!("- {}", Self::describe(&trigger)))
+            .collect::<Vec<_>>();
+
+        if conflicts.is_empty() {
+            return Ok(None);
+        }
+
+        debug!(
+            len = conflicts.len(),
+            "Trigger overlaps with existing triggers"
+        );
+
+        Ok(Some(SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!(
+                "This trigger overlaps with:\n{}\n{} Use `--force` to save it anyway.",
+                conflicts.join("\n"),
+                CONFLICT_EXPLANATION
+            )),
+        )))
+    }
+
+    fn describe(trigger: &models::Trigger) -> String {
+        format!(
+            "trigger {} ({}: `{}`) for member {}",
+            trigger.id, trigger.typ, trigger.text, trigger.member_id
+        )
+    }
+
+    fn invalid_channels_response(error: &str) -> SlackCommandEventResponse {
+        SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!("Invalid channels: {error}")),
+        )
+    }
+
+    fn invalid_trigger_response(error: &trigger::InvalidTrigger) -> SlackCommandEventResponse {
+        SlackCommandEventResponse::new(
+            SlackMessageContent::new().with_text(format!("Invalid trigger: {error}")),
+        )
+    }
 }
@@ -2,6 +2,8 @@
 //!
 //! This is where message rewriting, trigger detection, and message handling logic are implemented.
 
+mod rich_text;
+
 use std::{convert::Infallible, sync::Arc};
 
 use axum::{Extension, body::Bytes, http::Response};
@@ -12,16 +14,28 @@ use sqlx::SqlitePool;
 use tracing::{debug, error, info, trace, warn};
 
 use crate::{
-    BOT_TOKEN, fields,
-    models::{self, trigger, user},
+    BOT_TOKEN, announcements, avatar, fields,
+    models::{
+        self,
+        autoproxy::{ChannelAutoproxy, ThreadLatch},
+        switch,
+        trust::Trusted,
+        user,
+    },
+    status, util,
 };
 
+/// The most messages a single message can be split into. See [`split_message`]
+const MAX_SPLIT_SEGMENTS: usize = 10;
+
 #[derive(thiserror::Error, displaydoc::Display, Debug)]
 pub enum RewriteMessageError {
     /// Error while posting a message to Slack
     PostMessage,
     /// Error while deleting a message from Slack
     DeleteMessage,
+    /// Error while editing a message on Slack
+    EditMessage,
     /// Error while serializing custom image blocks
     SerializeImageBlocks,
     /// Error while saving message log to database
@@ -40,6 +54,8 @@ pub enum PushEventError {
     MemberChange,
     /// Error while attempting to rewrite the message
     MessageRewrite,
+    /// Error while setting a member's avatar from an uploaded image
+    Avatar,
 }
 
 #[tracing::instrument(skip(environment, event))]
@@ -100,12 +116,14 @@ async fn push_event_callback(
             debug!("Message log deleted");
             Ok(())
         }
+        // Messages with uploaded files (e.g. avatar replies) come in as file shares
         SlackEventCallbackBody::Message(message_event)
-            if message_event.subtype.is_none()
-                || message_event
-                    .subtype
-                    .as_ref()
-                    .is_some_and(|subtype| *subtype == SlackMessageEventType::MessageChanged) =>
+            if matches!(
+                message_event.subtype,
+                None | Some(
+                    SlackMessageEventType::MessageChanged | SlackMessageEventType::FileShare
+                )
+            ) =>
         {
             handle_message(message_event, &client, &state).await
         }
@@ -142,7 +160,14 @@ async fn handle_message(
 
     fields!(system_id = %&system.id);
 
-    let Some(ref channel_id) = message_event.origin.channel else {
+    // Any message counts as activity, proxied or not
+    if system.inactivity_timeout.is_some() {
+        models::revert::ScheduledRevert::postpone_inactivity(system.id, &user_state.db)
+            .await
+            .change_context(PushEventError::MemberChange)?;
+    }
+
+    let Some(channel_id) = message_event.origin.channel.clone() else {
         debug!("Failed to get channel ID");
         return Ok(());
     };
@@ -154,61 +179,211 @@ async fn handle_message(
         return Ok(());
     };
 
+    if avatar::handle_reply(
+        client,
+        system.id,
+        &channel_id,
+        &message_event.origin,
+        &content,
+        &user_state.db,
+    )
+    .await
+    .change_context(PushEventError::Avatar)?
+    {
+        debug!("Message was an avatar upload");
+        return Ok(());
+    }
+
+    proxy_message(
+        client,
+        message_event.origin,
+        &channel_id,
+        content,
+        &mut system,
+        &user_state.db,
+    )
+    .await
+}
+
+/// Decides who (if anyone) a message should be proxied as, and proxies it
+#[tracing::instrument(skip_all)]
+async fn proxy_message(
+    client: &SlackHyperClient,
+    origin: SlackMessageOrigin,
+    channel_id: &SlackChannelId,
+    content: SlackMessageContent,
+    system: &mut models::System,
+    db: &SqlitePool,
+) -> error_stack::Result<(), PushEventError> {
+    // Checked before anything else, so an escaped message can't switch the fronting member either
+    if let Some(ref message_content) = content.text
+        && util::unescape(message_content).starts_with(&system.proxy_escape)
+    {
+        debug!("Message starts with the proxy escape, not proxying");
+
+        return strip_proxy_escape(client, origin, content, system)
+            .await
+            .change_context(PushEventError::MessageRewrite);
+    }
+
+    let mut channel_autoproxy = ChannelAutoproxy::fetch(system.id, channel_id, db)
+        .await
+        .change_context(PushEventError::SystemFetch)?;
+    let thread_ts = origin.thread_ts.clone();
+    let thread = thread_ts.as_ref().map(|thread_ts| (channel_id, thread_ts));
+
+    if system.split_messages
+        && let Some(ref message_content) = content.text
+        && let Some(segments) = split_message(system, message_content, channel_id, db)
+            .await
+            .change_context(PushEventError::MemberFetch)?
+    {
+        debug!(segments = segments.len(), "Splitting message");
+
+        if let Some((member, _)) = segments.last() {
+            follow_trigger(
+                client,
+                system,
+                channel_autoproxy.as_mut(),
+                thread,
+                member.id,
+                db,
+            )
+            .await?;
+        }
+
+        return rewrite_split_message(client, origin, content, segments, system, db)
+            .await
+            .change_context(PushEventError::MessageRewrite);
+    }
+
     if let Some(ref message_content) = content.text
         && let Some(member) = system
-            .find_member_by_trigger_rules(&user_state.db, message_content)
+            .find_member_by_trigger_rules(db, message_content, channel_id)
             .await
             .change_context(PushEventError::MemberFetch)?
     {
         fields!(member = ?&member);
         debug!("Member triggered");
 
-        if system.auto_switch_on_trigger {
-            system
-                .change_fronting_member(Some(member.id), &user_state.db)
-                .await
-                .change_context(PushEventError::MemberChange)?;
-        }
-
-        rewrite_message(
+        follow_trigger(
             client,
-            message_event.origin,
-            content,
-            member,
-            &system,
-            &user_state.db,
+            system,
+            channel_autoproxy.as_mut(),
+            thread,
+            member.id,
+            db,
         )
-        .await
-        .change_context(PushEventError::MessageRewrite)?;
+        .await?;
+
+        rewrite_message(client, origin, content, member, system, db)
+            .await
+            .change_context(PushEventError::MessageRewrite)?;
 
         return Ok(());
     }
 
     debug!("Member not triggered");
 
-    // No triggers ran, so check if there's any actively fronting member
-    if let Some(member_id) = system.currently_fronting_member_id {
+    // No triggers ran, so check if autoproxy sends it as anyone
+    let autoproxy_member_id =
+        autoproxy_member_id(system, channel_autoproxy.as_ref(), thread, db).await?;
+
+    if let Some(member_id) = autoproxy_member_id {
         fields!(member = %&member_id);
-        let member = models::Member::fetch_by_id(member_id, &user_state.db)
+        let member = models::Member::fetch_by_id(member_id, db)
             .await
             .change_context(PushEventError::MemberFetch)?;
         fields!(member = ?&member);
 
-        rewrite_message(
-            client,
-            message_event.origin,
-            content,
-            member.into(),
-            &system,
-            &user_state.db,
-        )
-        .await
-        .change_context(PushEventError::MemberFetch)?;
+        if !member.enabled {
+            debug!("Autoproxy member is disabled, not proxying");
+            return Ok(());
+        }
+
+        rewrite_message(client, origin, content, member.into(), system, db)
+            .await
+            .change_context(PushEventError::MemberFetch)?;
     }
 
     Ok(())
 }
 
+/// The member a message without a trigger is sent as, if any.
+///
+/// A thread's latch takes precedence over the channel's own setting, which takes precedence over the system's.
+/// Using a thread's latch keeps it from expiring.
+async fn autoproxy_member_id(
+    system: &models::System,
+    channel_autoproxy: Option<&ChannelAutoproxy>,
+    thread: Option<(&SlackChannelId, &SlackTs)>,
+    db: &SqlitePool,
+) -> error_stack::Result<Option<models::member::Id<Trusted>>, PushEventError> {
+    if let Some(timeout) = system.thread_latch_timeout()
+        && let Some((channel_id, thread_ts)) = thread
+        && let Some(latch) = ThreadLatch::fetch(system.id, channel_id, thread_ts, db)
+            .await
+            .change_context(PushEventError::SystemFetch)?
+            .filter(|latch| !latch.is_expired(timeout))
+    {
+        debug!(member_id = %latch.member_id, "Using thread latch");
+
+        ThreadLatch::upsert(system.id, channel_id, thread_ts, latch.member_id, db)
+            .await
+            .change_context(PushEventError::MemberChange)?;
+
+        return Ok(Some(latch.member_id));
+    }
+
+    Ok(channel_autoproxy.map_or_else(
+        || system.autoproxy_member_id(),
+        |channel_autoproxy| channel_autoproxy.member_id(system),
+    ))
+}
+
+/// Switches the front to, and latches onto, a member who used a trigger, depending on the system's settings.
+///
+/// In a channel with its own autoproxy setting, only the channel's latch is used.
+/// In a thread with thread latching on, only the thread is latched onto the member.
+async fn follow_trigger(
+    client: &SlackHyperClient,
+    system: &mut models::System,
+    channel_autoproxy: Option<&mut ChannelAutoproxy>,
+    thread: Option<(&SlackChannelId, &SlackTs)>,
+    member_id: models::member::Id<Trusted>,
+    db: &SqlitePool,
+) -> error_stack::Result<(), PushEventError> {
+    if system.auto_switch_on_trigger
+        && system
+            .change_fronting_member(Some(member_id), switch::Source::Trigger, db)
+            .await
+            .change_context(PushEventError::MemberChange)?
+    {
+        announcements::announce_switch(client, system.id, switch::Source::Trigger, db).await;
+        status::mirror_front(client, system.id, db).await;
+    }
+
+    if let Some(timeout) = system.thread_latch_timeout()
+        && let Some((channel_id, thread_ts)) = thread
+    {
+        ThreadLatch::delete_expired(system.id, timeout, db)
+            .await
+            .change_context(PushEventError::MemberChange)?;
+        ThreadLatch::upsert(system.id, channel_id, thread_ts, member_id, db)
+            .await
+            .change_context(PushEventError::MemberChange)?;
+
+        // The latch is for that thread only, so the channel or system latch stays as it was
+        return Ok(());
+    }
+
+    match channel_autoproxy {
+        Some(channel_autoproxy) => channel_autoproxy.latch(member_id, db).await,
+        None => system.latch(member_id, db).await,
+    }
+    .change_context(PushEventError::MemberChange)
+}
+
 #[tracing::instrument(skip(client, db, system), fields(system_id = %system.id))]
 async fn rewrite_message(
     client: &SlackHyperClient,
@@ -231,6 +406,142 @@ async fn rewrite_message(
 
     rewrite_content(&mut content, &member);
 
+    post_as_member(
+        &bot_session,
+        &channel_id,
+        origin.thread_ts.as_ref(),
+        content,
+        &member,
+        system,
+        db,
+    )
+    .await?;
+
+    user_session
+        .chat_delete(
+            &SlackApiChatDeleteRequest::new(channel_id.clone(), origin.ts).with_as_user(true),
+        )
+        .await
+        .change_context(RewriteMessageError::DeleteMessage)?;
+
+    Ok(())
+}
+
+/// Like [`rewrite_message`], but sends each segment from [`split_message`] as its own message, in order
+#[tracing::instrument(skip_all, fields(system_id = %system.id))]
+async fn rewrite_split_message(
+    client: &SlackHyperClient,
+    origin: SlackMessageOrigin,
+    mut content: SlackMessageContent,
+    segments: Vec<(models::DetectedMember, SlackMessageContent)>,
+    system: &models::System,
+    db: &SqlitePool,
+) -> error_stack::Result<(), RewriteMessageError> {
+    info!("Rewriting split message");
+    let Some(channel_id) = origin.channel else {
+        warn!("No channel ID found in origin. Bot possibly doesn't have access. Bailing");
+        return Ok(());
+    };
+
+    let token = SlackApiToken::new(system.slack_oauth_token.expose().into())
+        .with_token_type(SlackApiTokenType::User);
+    let user_session = client.open_session(&token);
+    let bot_session = client.open_session(&BOT_TOKEN);
+
+    let last = segments.len() - 1;
+
+    for (i, (member, mut segment)) in segments.into_iter().enumerate() {
+        // Any files go with the last segment, as that's where they'd appear in the original message
+        if i == last {
+            segment.files = content.files.take();
+        }
+
+        post_as_member(
+            &bot_session,
+            &channel_id,
+            origin.thread_ts.as_ref(),
+            segment,
+            &member,
+            system,
+            db,
+        )
+        .await?;
+    }
+
+    user_session
+        .chat_delete(
+            &SlackApiChatDeleteRequest::new(channel_id.clone(), origin.ts).with_as_user(true),
+        )
+        .await
+        .change_context(RewriteMessageError::DeleteMessage)?;
+
+    Ok(())
+}
+
+/// Removes the proxy escape from a message by editing it with the user's token, if the system wants it removed
+#[tracing::instrument(skip_all, fields(system_id = %system.id))]
+async fn strip_proxy_escape(
+    client: &SlackHyperClient,
+    origin: SlackMessageOrigin,
+    content: SlackMessageContent,
+    system: &models::System,
+) -> error_stack::Result<(), RewriteMessageError> {
+    if !system.strip_proxy_escape {
+        return Ok(());
+    }
+
+    let Some(channel_id) = origin.channel else {
+        warn!("No channel ID found in origin. Bot possibly doesn't have access. Bailing");
+        return Ok(());
+    };
+
+    let Some(text) = content
+        .text
+        .as_deref()
+        .and_then(|text| text.strip_prefix(&util::escape(&system.proxy_escape)))
+    else {
+        return Ok(());
+    };
+
+    if text.trim().is_empty() {
+        debug!("Nothing left after removing the proxy escape, leaving the message as is");
+        return Ok(());
+    }
+
+    let mut blocks = content.blocks;
+
+    if let Some(blocks) = &mut blocks {
+        rich_text::strip_tags(blocks, &system.proxy_escape, "");
+    }
+
+    let token = SlackApiToken::new(system.slack_oauth_token.expose().into())
+        .with_token_type(SlackApiTokenType::User);
+
+    client
+        .open_session(&token)
+        .chat_update(&SlackApiChatUpdateRequest::new(
+            channel_id,
+            SlackMessageContent::new()
+                .with_text(text.to_owned())
+                .opt_blocks(blocks),
+            origin.ts,
+        ))
+        .await
+        .change_context(RewriteMessageError::EditMessage)?;
+
+    Ok(())
+}
+
+/// Posts already rewritten content under a member's name and profile picture, and logs it
+async fn post_as_member(
+    bot_session: &SlackClientSession<'_, SlackClientHyperHttpsConnector>,
+    channel_id: &SlackChannelId,
+    thread_ts: Option<&SlackTs>,
+    mut content: SlackMessageContent,
+    member: &models::DetectedMember,
+    system: &models::System,
+    db: &SqlitePool,
+) -> error_stack::Result<(), RewriteMessageError> {
     let mut custom_image_blocks = Vec::new();
 
     if let Some(files) = content.files.take() {
@@ -283,20 +594,23 @@ async fn rewrite_message(
     }
 
     let message_request = SlackApiChatPostMessageRequest::new(channel_id.clone(), content)
-        .opt_thread_ts(origin.thread_ts)
-        .with_username(member.display_name.clone())
+        .opt_thread_ts(thread_ts.cloned())
+        .with_username(system.proxy_username(&member.display_name, member.show_system_tag))
         .opt_icon_url(member.profile_picture_url.clone());
 
     let mut request = serde_json::to_value(message_request).unwrap();
 
-    let blocks = request.get_mut("blocks").unwrap().as_array_mut().unwrap();
-    let custom_image_blocks = custom_image_blocks
-        .into_iter()
-        .map(serde_json::to_value)
-        .collect::<std::result::Result<Vec<serde_json::Value>, serde_json::Error>>()
-        .change_context(RewriteMessageError::SerializeImageBlocks)?;
+    // Split message segments have no blocks at all, so only touch them if there's something to add
+    if !custom_image_blocks.is_empty() {
+        let blocks = request.get_mut("blocks").unwrap().as_array_mut().unwrap();
+        let custom_image_blocks = custom_image_blocks
+            .into_iter()
+            .map(serde_json::to_value)
+            .collect::<std::result::Result<Vec<serde_json::Value>, serde_json::Error>>()
+            .change_context(RewriteMessageError::SerializeImageBlocks)?;
 
-    blocks.extend(custom_image_blocks);
+        blocks.extend(custom_image_blocks);
+    }
 
     let res: SlackApiChatPostMessageResponse = bot_session
         .http_session_api
@@ -312,68 +626,102 @@ async fn rewrite_message(
         .await
         .change_context(RewriteMessageError::MessageLog)?;
 
-    user_session
-        .chat_delete(
-            &SlackApiChatDeleteRequest::new(channel_id.clone(), origin.ts).with_as_user(true),
-        )
-        .await
-        .change_context(RewriteMessageError::DeleteMessage)?;
-
     Ok(())
 }
 
-fn rewrite_content(content: &mut SlackMessageContent, member: &models::DetectedMember) {
-    debug!("Rewriting message content");
+/// Splits a message into one segment per line that starts with a trigger, with the trigger removed.
+/// Lines without a trigger stay with the segment before them.
+///
+/// Returns `None` if the message shouldn't be split: if it doesn't start with a trigger,
+/// only has one segment, or has more than [`MAX_SPLIT_SEGMENTS`].
+/// Segments that are empty once the trigger is removed are dropped, as Slack won't send an empty message.
+/// Rich text formatting is dropped, as the segments are sent as plain `text`.
+#[tracing::instrument(skip_all, fields(system_id = %system.id))]
+async fn split_message(
+    system: &models::System,
+    text: &str,
+    channel_id: &SlackChannelId,
+    db: &SqlitePool,
+) -> error_stack::Result<Option<Vec<(models::DetectedMember, SlackMessageContent)>>, sqlx::Error> {
+    let mut segments: Vec<(models::DetectedMember, SlackMessageContent)> = Vec::new();
 
-    if let Some(text) = &mut content.text {
-        match member.typ {
-            trigger::Type::Prefix => {
-                if let Some(new_text) = text.strip_prefix(&member.trigger_text) {
-                    *text = new_text.to_string();
-                }
+    for line in text.lines() {
+        if let Some(member) = system
+            .find_member_by_trigger_rules(db, line, channel_id)
+            .await?
+        {
+            if segments.len() == MAX_SPLIT_SEGMENTS {
+                debug!("Too many segments, not splitting");
+                return Ok(None);
             }
-            trigger::Type::Suffix => {
-                if let Some(new_text) = text.strip_suffix(&member.trigger_text) {
-                    *text = new_text.to_string();
-                }
+
+            let mut segment = SlackMessageContent::new().with_text(line.to_owned());
+            rewrite_content(&mut segment, &member);
+            segments.push((member, segment));
+        } else if let Some((_, segment)) = segments.last_mut()
+            && let Some(segment_text) = &mut segment.text
+        {
+            if !segment_text.is_empty() {
+                segment_text.push('\n');
             }
+            segment_text.push_str(line);
+        } else {
+            debug!("Message doesn't start with a trigger, not splitting");
+            return Ok(None);
         }
     }
 
-    if let Some(blocks) = &mut content.blocks {
-        for block in blocks {
-            if let SlackBlock::RichText(richtext) = block {
-                let elements = richtext["elements"].as_array_mut().unwrap();
-                let len = elements.len();
-                // The first and last elements would have the prefix and suffix respectively, so we can filter them
-                let first = elements.get_mut(0).unwrap();
-
-                if let Some(first_text) = first.pointer_mut("/elements/0/text") {
-                    if member.typ == trigger::Type::Prefix {
-                        if let Some(new_text) = first_text
-                            .as_str()
-                            .and_then(|text| text.strip_prefix(&member.trigger_text))
-                            .map(ToString::to_string)
-                        {
-                            *first_text = serde_json::Value::String(new_text);
-                        }
-                    }
-                }
+    segments.retain(|(_, segment)| {
+        segment
+            .text
+            .as_deref()
+            .is_some_and(|text| !text.trim().is_empty())
+    });
 
-                let last = elements.get_mut(len - 1).unwrap();
-
-                if let Some(last_text) = last.pointer_mut("/elements/0/text") {
-                    if member.typ == trigger::Type::Suffix {
-                        if let Some(new_text) = last_text
-                            .as_str()
-                            .and_then(|text| text.strip_suffix(&member.trigger_text))
-                            .map(ToString::to_string)
-                        {
-                            *last_text = serde_json::Value::String(new_text);
-                        }
-                    }
-                }
-            }
-        }
+    Ok((segments.len() > 1).then_some(segments))
+}
+
+fn rewrite_content(content: &mut SlackMessageContent, member: &models::DetectedMember) {
+    debug!("Rewriting message content");
+
+    let Some(text) = &mut content.text else {
+        return;
+    };
+
+    let Some(content_range) = member.content.clone() else {
+        return;
+    };
+
+    let unescaped_text = util::unescape(text);
+
+    // These are unescaped, as rich text blocks aren't escaped like the text field is
+    let (Some(prefix), Some(suffix)) = (
+        unescaped_text.get(..content_range.start),
+        unescaped_text.get(content_range.end..),
+    ) else {
+        return;
+    };
+
+    if let Some(new_text) = text
+        .strip_prefix(&util::escape(prefix))
+        .and_then(|text| text.strip_suffix(&util::escape(suffix)))
+    {
+        // Same as the rich text, whitespace next to the tags goes with them
+        let new_text = if prefix.is_empty() {
+            new_text
+        } else {
+            new_text.trim_start()
+        };
+        let new_text = if suffix.is_empty() {
+            new_text
+        } else {
+            new_text.trim_end()
+        };
+
+        *text = new_text.to_owned();
+    }
+
+    if let Some(blocks) = &mut content.blocks {
+        rich_text::strip_tags(blocks, prefix, suffix);
     }
 }
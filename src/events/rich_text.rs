@@ -0,0 +1,214 @@
+//! Rewriting of Slack rich text blocks.
+//!
+//! Rich text is a tree: a block holds sections, lists, quotes and preformatted elements, which hold leaf elements
+//! like text, emoji, mentions and links (lists nest one level deeper). Unlike the `text` field of a message,
+//! rich text isn't HTML escaped.
+
+use serde_json::Value;
+use slack_morphism::prelude::*;
+use tracing::debug;
+
+/// Strips `prefix` from the start and `suffix` from the end of the rich text in `blocks`,
+/// along with any whitespace next to them. Elements that end up empty are removed.
+///
+/// The prefix is taken from the first rich text block and the suffix from the last one.
+/// If a tag can't be found where it's expected, that block is left as it was.
+pub fn strip_tags(blocks: &mut Vec<SlackBlock>, prefix: &str, suffix: &str) {
+    let rich_text_blocks = blocks
+        .iter()
+        .enumerate()
+        .filter(|(_, block)| matches!(block, SlackBlock::RichText(_)))
+        .map(|(i, _)| i)
+        .collect::<Vec<_>>();
+
+    let (Some(&first), Some(&last)) = (rich_text_blocks.first(), rich_text_blocks.last()) else {
+        return;
+    };
+
+    if !prefix.is_empty() {
+        rewrite_block(&mut blocks[first], |leaves| strip_start(leaves, prefix));
+    }
+
+    if !suffix.is_empty() {
+        rewrite_block(&mut blocks[last], |leaves| strip_end(leaves, suffix));
+    }
+
+    blocks.retain(|block| match block {
+        SlackBlock::RichText(rich_text) => has_content(rich_text),
+        _ => true,
+    });
+}
+
+/// Runs `rewrite` on a copy of the block's leaves, only keeping the changes if it succeeds
+fn rewrite_block(block: &mut SlackBlock, rewrite: impl FnOnce(&mut [&mut Value]) -> Option<()>) {
+    let SlackBlock::RichText(rich_text) = block else {
+        return;
+    };
+
+    let mut rewritten = rich_text.clone();
+    let mut leaves = Vec::new();
+    collect_leaves(&mut rewritten, &mut leaves);
+
+    if rewrite(&mut leaves).is_some() {
+        prune(&mut rewritten);
+        *rich_text = rewritten;
+    } else {
+        debug!("Couldn't find trigger tag in rich text, leaving it as is");
+    }
+}
+
+/// Collects the leaf elements of a rich text tree in reading order
+fn collect_leaves<'a>(value: &'a mut Value, leaves: &mut Vec<&'a mut Value>) {
+    if value.get("elements").is_some_and(Value::is_array) {
+        for element in value["elements"].as_array_mut().into_iter().flatten() {
+            collect_leaves(element, leaves);
+        }
+    } else {
+        leaves.push(value);
+    }
+}
+
+/// Removes emptied text leaves, then any containers left without elements.
+/// Returns whether the element itself should be kept
+fn prune(value: &mut Value) -> bool {
+    if let Some(elements) = value.get_mut("elements").and_then(Value::as_array_mut) {
+        elements.retain_mut(prune);
+        return !elements.is_empty();
+    }
+
+    !is_text(value) || value.get("text").and_then(Value::as_str) != Some("")
+}
+
+fn has_content(value: &Value) -> bool {
+    value
+        .get("elements")
+        .and_then(Value::as_array)
+        .is_some_and(|elements| !elements.is_empty())
+}
+
+fn is_text(leaf: &Value) -> bool {
+    leaf.get("type").and_then(Value::as_str) == Some("text")
+}
+
+/// How a non-text leaf appears in a message's `text` field, which is what triggers are matched against
+fn leaf_text(leaf: &Value) -> Option<String> {
+    let field = |name: &str| leaf.get(name).and_then(Value::as_str);
+
+    match leaf.get("type").and_then(Value::as_str)? {
+        "text" => field("text").map(ToString::to_string),
+        "emoji" => field("name").map(|name| format!(":{name}:")),
+        "user" => field("user_id").map(|id| format!("<@{id}>")),
+        "channel" => field("channel_id").map(|id| format!("<#{id}>")),
+        "usergroup" => field("usergroup_id").map(|id| format!("<!subteam^{id}>")),
+        "broadcast" => field("range").map(|range| format!("<!{range}>")),
+        _ => None,
+    }
+}
+
+/// Replaces a leaf with empty text, so [`prune`] removes it
+fn clear(leaf: &mut Value) {
+    *leaf = serde_json::json!({ "type": "text", "text": "" });
+}
+
+fn set_text(leaf: &mut Value, text: &str) {
+    leaf["text"] = Value::String(text.to_owned());
+}
+
+/// Strips `tag` from the start of the leaves, then any whitespace after it.
+/// Returns `None` if the leaves don't start with the tag, in which case they may be partly rewritten
+fn strip_start(leaves: &mut [&mut Value], tag: &str) -> Option<()> {
+    let mut rest = tag;
+    let mut leaves = leaves.iter_mut();
+
+    while !rest.is_empty() {
+        let leaf = leaves.next()?;
+        let text = leaf_text(leaf)?;
+
+        if let Some(remaining) = rest.strip_prefix(text.as_str()) {
+            rest = remaining;
+            clear(leaf);
+        } else if is_text(leaf)
+            && let Some(remaining) = text.strip_prefix(rest)
+        {
+            rest = "";
+            set_text(leaf, remaining.trim_start());
+
+            if !remaining.trim_start().is_empty() {
+                return Some(());
+            }
+        } else {
+            return None;
+        }
+    }
+
+    // Whitespace is only stripped up to the first mention, emoji, etc.
+    for leaf in leaves {
+        let Some(text) = leaf
+            .get("text")
+            .and_then(Value::as_str)
+            .filter(|_| is_text(leaf))
+            .map(|text| text.trim_start().to_owned())
+        else {
+            break;
+        };
+
+        set_text(leaf, &text);
+
+        if !text.is_empty() {
+            break;
+        }
+    }
+
+    Some(())
+}
+
+/// Strips `tag` from the end of the leaves, then any whitespace before it.
+/// Returns `None` if the leaves don't end with the tag, in which case they may be partly rewritten
+fn strip_end(leaves: &mut [&mut Value], tag: &str) -> Option<()> {
+    let mut rest = tag;
+    let mut leaves = leaves.iter_mut().rev();
+
+    while !rest.is_empty() {
+        let leaf = leaves.next()?;
+        let text = leaf_text(leaf)?;
+
+        if let Some(remaining) = rest.strip_suffix(text.as_str()) {
+            rest = remaining;
+            clear(leaf);
+        } else if is_text(leaf)
+            && let Some(remaining) = text.strip_suffix(rest)
+        {
+            rest = "";
+            set_text(leaf, remaining.trim_end());
+
+            if !remaining.trim_end().is_empty() {
+                return Some(());
+            }
+        } else {
+            return None;
+        }
+    }
+
+    // Whitespace is only stripped up to the first mention, emoji, etc.
+    for leaf in leaves {
+        let Some(text) = leaf
+            .get("text")
+            .and_then(Value::as_str)
+            .filter(|_| is_text(leaf))
+            .map(|text| text.trim_end().to_owned())
+        else {
+            break;
+        };
+
+        set_text(leaf, &text);
+
+        if !text.is_empty() {
+            break;
+        }
+    }
+
+    Some(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,188 @@
+//! Tests against rich text captured from real Slack messages, in `fixtures/`
+
+use serde_json::{Value, json};
+use slack_morphism::prelude::*;
+
+use super::{collect_leaves, prune, strip_end, strip_start, strip_tags};
+
+fn fixture(json: &str) -> Vec<SlackBlock> {
+    serde_json::from_str(json).expect("fixture should be valid blocks")
+}
+
+/// The elements of each rich text block
+fn elements(blocks: &[SlackBlock]) -> Vec<Value> {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            SlackBlock::RichText(rich_text) => Some(rich_text["elements"].clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn rich_text(blocks: &[SlackBlock]) -> Value {
+    match &blocks[0] {
+        SlackBlock::RichText(rich_text) => rich_text.clone(),
+        other => panic!("expected a rich text block, got {other:?}"),
+    }
+}
+
+#[test]
+fn strips_leading_mention_and_emoji() {
+    let mut blocks = fixture(include_str!("fixtures/leading_mention.json"));
+
+    strip_tags(&mut blocks, "<@U07A1B2C3D4> :sparkles:", "");
+
+    assert_eq!(
+        elements(&blocks),
+        [json!([{
+            "type": "rich_text_section",
+            "elements": [
+                { "type": "text", "text": "have you seen " },
+                { "type": "link", "url": "https://github.com/Suya1671/plura", "text": "this" },
+                { "type": "text", "text": "?" }
+            ]
+        }])]
+    );
+}
+
+#[test]
+fn strips_tag_inside_code_block() {
+    let mut blocks = fixture(include_str!("fixtures/code_block.json"));
+
+    strip_tags(&mut blocks, "a:", "");
+
+    assert_eq!(
+        elements(&blocks),
+        [json!([{
+            "type": "rich_text_preformatted",
+            "elements": [{ "type": "text", "text": "let answer = 42;" }],
+            "border": 0
+        }])]
+    );
+}
+
+#[test]
+fn strips_tag_inside_quote() {
+    let mut blocks = fixture(include_str!("fixtures/quote.json"));
+
+    strip_tags(&mut blocks, "", "-a");
+
+    assert_eq!(
+        elements(&blocks),
+        [json!([{
+            "type": "rich_text_quote",
+            "elements": [{ "type": "text", "text": "the past is never dead" }]
+        }])]
+    );
+}
+
+#[test]
+fn removes_first_element_left_empty() {
+    let mut blocks = fixture(include_str!("fixtures/tag_only_first_line.json"));
+
+    strip_tags(&mut blocks, "a:", "");
+
+    assert_eq!(
+        elements(&blocks),
+        [json!([{
+            "type": "rich_text_list",
+            "style": "bullet",
+            "indent": 0,
+            "border": 0,
+            "elements": [
+                {
+                    "type": "rich_text_section",
+                    "elements": [{ "type": "text", "text": "milk" }]
+                },
+                {
+                    "type": "rich_text_section",
+                    "elements": [{ "type": "text", "text": "eggs" }]
+                }
+            ]
+        }])]
+    );
+}
+
+#[test]
+fn trims_whitespace_next_to_tags() {
+    let mut blocks = fixture(include_str!("fixtures/whitespace.json"));
+
+    strip_tags(&mut blocks, "[", "]");
+
+    assert_eq!(
+        elements(&blocks),
+        [json!([{
+            "type": "rich_text_section",
+            "elements": [
+                { "type": "text", "text": "hello", "style": { "bold": true } },
+                { "type": "text", "text": " there" }
+            ]
+        }])]
+    );
+}
+
+#[test]
+fn missing_tag_returns_none() {
+    let blocks = fixture(include_str!("fixtures/untagged.json"));
+
+    let mut value = rich_text(&blocks);
+    let mut leaves = Vec::new();
+    collect_leaves(&mut value, &mut leaves);
+    assert_eq!(strip_start(&mut leaves, "b:"), None);
+
+    let mut value = rich_text(&blocks);
+    let mut leaves = Vec::new();
+    collect_leaves(&mut value, &mut leaves);
+    assert_eq!(strip_end(&mut leaves, "-b"), None);
+}
+
+#[test]
+fn missing_tag_leaves_blocks_untouched() {
+    let original = fixture(include_str!("fixtures/untagged.json"));
+    let mut blocks = original.clone();
+
+    strip_tags(&mut blocks, "b:", "-b");
+
+    assert_eq!(blocks, original);
+}
+
+#[test]
+fn prune_removes_empty_text_and_containers() {
+    let mut value = json!({
+        "elements": [
+            {
+                "type": "rich_text_section",
+                "elements": [{ "type": "text", "text": "" }]
+            },
+            {
+                "type": "rich_text_section",
+                "elements": [
+                    { "type": "emoji", "name": "wave" },
+                    { "type": "text", "text": "" }
+                ]
+            }
+        ]
+    });
+
+    assert!(prune(&mut value));
+    assert_eq!(
+        value,
+        json!({
+            "elements": [{
+                "type": "rich_text_section",
+                "elements": [{ "type": "emoji", "name": "wave" }]
+            }]
+        })
+    );
+}
+
+#[test]
+fn prune_drops_emptied_element() {
+    let mut value = json!({
+        "type": "rich_text_quote",
+        "elements": [{ "type": "text", "text": "" }]
+    });
+
+    assert!(!prune(&mut value));
+}
@@ -0,0 +1,290 @@
+//! Check-in reminders, sent when the front hasn't changed for a system's check-in interval.
+//!
+//! The reminder has buttons to keep the front, switch to another member, or switch to the base account.
+//! See [`ScheduledCheckIn`]
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{debug, warn};
+
+use crate::{
+    BOT_TOKEN, announcements, fields,
+    models::{
+        check_in::ScheduledCheckIn,
+        member::{self, describe_front},
+        switch::{self, Switch},
+        system::{self, System},
+        trust::Trusted,
+        user,
+    },
+    status,
+    util::format_duration,
+};
+
+const KEEP_ACTION_ID: &str = "check_in_keep";
+const SWITCH_ACTION_ID: &str = "check_in_switch";
+const BASE_ACTION_ID: &str = "check_in_base";
+
+/// Slack won't show more options than this in a select menu
+const MAX_SELECT_OPTIONS: usize = 100;
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while calling the Slack API
+    Slack,
+}
+
+/// Whether an action ID belongs to a check-in reminder
+pub fn is_check_in_action(action_id: &SlackActionId) -> bool {
+    [KEEP_ACTION_ID, SWITCH_ACTION_ID, BASE_ACTION_ID].contains(&&*action_id.0)
+}
+
+/// DMs the owner of a system a check-in reminder, if anyone is still fronting
+#[tracing::instrument(skip(client, db))]
+pub async fn send_reminder(
+    check_in: &ScheduledCheckIn,
+    client: &SlackHyperClient,
+    db: &SqlitePool,
+) -> Result<(), Error> {
+    let system = check_in
+        .system_id
+        .fetch(db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    let fronting = check_in
+        .system_id
+        .fronting_members(db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    let Some(front) = describe_front(&fronting) else {
+        debug!("Nobody is fronting, not sending a check-in");
+        return Ok(());
+    };
+
+    let since = Switch::fetch_by_system_id(check_in.system_id, None, None, 1, db)
+        .await
+        .change_context(Error::Sqlx)?
+        .into_iter()
+        .next()
+        .map(|switch| time::OffsetDateTime::now_utc() - switch.created_at.assume_utc());
+
+    let text = since.map_or_else(
+        || format!("{front} is still fronting. Is that still right?"),
+        |since| {
+            format!(
+                "{front} has been fronting for {}. Is that still right?",
+                format_duration(since)
+            )
+        },
+    );
+
+    let members = system.members(db).await.change_context(Error::Sqlx)?;
+
+    let options = members
+        .iter()
+        .filter(|member| member.enabled)
+        .take(MAX_SELECT_OPTIONS)
+        .map(|member| {
+            SlackBlockChoiceItem::<SlackBlockPlainTextOnly>::new(
+                member.display_name.clone().into(),
+                member.id.to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let mut elements: Vec<SlackActionBlockElement> =
+        vec![SlackBlockButtonElement::new(KEEP_ACTION_ID.into(), "Keep".into()).into()];
+
+    if !options.is_empty() {
+        elements.push(
+            SlackBlockStaticSelectElement::new(SWITCH_ACTION_ID.into())
+                .with_placeholder("Switch to…".into())
+                .with_options(options)
+                .into(),
+        );
+    }
+
+    elements.push(
+        SlackBlockButtonElement::new(BASE_ACTION_ID.into(), "Switch to base account".into()).into(),
+    );
+
+    let session = client.open_session(&BOT_TOKEN);
+    let user: SlackUserId = system.owner_id.into();
+
+    let conversation = session
+        .conversations_open(&SlackApiConversationsOpenRequest::new().with_users(vec![user]))
+        .await
+        .change_context(Error::Slack)?
+        .channel;
+
+    session
+        .chat_post_message(&SlackApiChatPostMessageRequest::new(
+            conversation.id,
+            SlackMessageContent::new()
+                .with_text(text.clone())
+                .with_blocks(vec![
+                    SlackSectionBlock::new()
+                        .with_text(SlackBlockText::Plain(text.into()))
+                        .into(),
+                    SlackActionsBlock::new(elements).into(),
+                ]),
+        ))
+        .await
+        .change_context(Error::Slack)?;
+
+    Ok(())
+}
+
+/// Handles a button or the member select on a check-in reminder,
+/// then replaces the reminder's buttons with what happened
+#[tracing::instrument(skip(event, client, db), fields(action_id))]
+pub async fn handle_action(
+    event: SlackInteractionBlockActionsEvent,
+    client: &SlackHyperClient,
+    db: &SqlitePool,
+) -> Result<(), Error> {
+    let Some(user) = event.user else {
+        warn!("Check-in action without a user");
+        return Ok(());
+    };
+
+    let Some(action) = event.actions.into_iter().flatten().next() else {
+        warn!("Check-in action event without an action");
+        return Ok(());
+    };
+
+    fields!(action_id = %&action.action_id);
+
+    let SlackInteractionActionContainer::Message(container) = event.container else {
+        warn!("Check-in action didn't come from a message");
+        return Ok(());
+    };
+
+    let Some(channel_id) = container.channel_id else {
+        warn!("Check-in message has no channel");
+        return Ok(());
+    };
+
+    let user_id: user::Id<Trusted> = user.id.clone().into();
+
+    let Some(system) = System::fetch_by_user_id(&user_id, db)
+        .await
+        .change_context(Error::Sqlx)?
+    else {
+        warn!("System not found for user. This should not happen. bailing");
+        return Ok(());
+    };
+
+    let text = match &*action.action_id.0 {
+        KEEP_ACTION_ID => {
+            let mut connection = db
+                .acquire()
+                .await
+                .attach_printable("Failed to acquire connection")
+                .change_context(Error::Sqlx)?;
+
+            ScheduledCheckIn::schedule(system.id, &mut connection)
+                .await
+                .change_context(Error::Sqlx)?;
+
+            "Kept the current front.".to_string()
+        }
+        SWITCH_ACTION_ID => {
+            let Some(member_id) = action
+                .selected_option
+                .and_then(|option| option.value.parse::<i64>().ok())
+                .map(member::Id::new)
+            else {
+                warn!("Check-in switch without a selected member");
+                return Ok(());
+            };
+
+            let member_id = member_id
+                .validate_by_system(system.id, db)
+                .await
+                .change_context(Error::Sqlx)?;
+
+            let enabled = match member_id {
+                Some(member_id) => member_id.enabled(db).await.change_context(Error::Sqlx)?,
+                None => false,
+            };
+
+            let (Some(member_id), true) = (member_id, enabled) else {
+                debug!("Selected member is missing or disabled");
+
+                client
+                    .open_session(&BOT_TOKEN)
+                    .chat_post_ephemeral(&SlackApiChatPostEphemeralRequest::new(
+                        channel_id,
+                        user.id,
+                        SlackMessageContent::new().with_text(
+                            "That member can't be switched to anymore. They may have been disabled or deleted.".into(),
+                        ),
+                    ))
+                    .await
+                    .change_context(Error::Slack)?;
+
+                return Ok(());
+            };
+
+            switch_front(client, system.id, &[member_id], db).await?;
+
+            let member = member_id.fetch(db).await.change_context(Error::Sqlx)?;
+            format!("Switched to {}.", member.display_name)
+        }
+        BASE_ACTION_ID => {
+            switch_front(client, system.id, &[], db).await?;
+            "Switched to your base account.".to_string()
+        }
+        id => {
+            warn!(id, "Unknown check-in action ID");
+            return Ok(());
+        }
+    };
+
+    client
+        .open_session(&BOT_TOKEN)
+        .chat_update(&SlackApiChatUpdateRequest::new(
+            channel_id,
+            SlackMessageContent::new().with_text(text),
+            container.message_ts,
+        ))
+        .await
+        .change_context(Error::Slack)?;
+
+    Ok(())
+}
+
+/// Switches the front from a check-in, rescheduling the check-in even if the front didn't change
+async fn switch_front(
+    client: &SlackHyperClient,
+    system_id: system::Id<Trusted>,
+    fronters: &[member::Id<Trusted>],
+    db: &SqlitePool,
+) -> Result<(), Error> {
+    let changed = system_id
+        .switch_front(fronters, switch::Source::Command, db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    if changed {
+        announcements::announce_switch(client, system_id, switch::Source::Command, db).await;
+        status::mirror_front(client, system_id, db).await;
+        return Ok(());
+    }
+
+    let mut connection = db
+        .acquire()
+        .await
+        .attach_printable("Failed to acquire connection")
+        .change_context(Error::Sqlx)?;
+
+    ScheduledCheckIn::schedule(system_id, &mut connection)
+        .await
+        .change_context(Error::Sqlx)
+}
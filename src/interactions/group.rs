@@ -0,0 +1,144 @@
+use error_stack::{Result, ResultExt, bail, ensure};
+use slack_morphism::prelude::*;
+use tracing::trace;
+
+use crate::{
+    BOT_TOKEN, fields,
+    models::{
+        group,
+        system::System,
+        trust::Trusted,
+        user::{self, State},
+    },
+};
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum Error {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while calling the Slack API
+    Slack,
+    /// Unable to parse view
+    ParsingView,
+    /// No system found for the user
+    NoSystem,
+    /// Group names can't contain spaces or be just a number
+    InvalidName,
+    /// You already have a group with that name
+    NameTaken,
+}
+
+/// Checks the view's name is valid, and not used by another of the system's groups
+async fn validate_name(
+    data: &group::View,
+    system_id: crate::models::system::Id<Trusted>,
+    group_id: Option<group::Id<Trusted>>,
+    user_state: &State,
+) -> Result<(), Error> {
+    ensure!(data.has_valid_name(), Error::InvalidName);
+
+    let existing = group::Id::fetch_by_name(&data.name, system_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    ensure!(existing.is_none() || existing == group_id, Error::NameTaken);
+
+    Ok(())
+}
+
+/// Lets the user know the view was submitted
+async fn confirm(
+    client: &SlackHyperClient,
+    user_id: user::Id<Trusted>,
+    text: String,
+) -> Result<(), Error> {
+    let session = client.open_session(&BOT_TOKEN);
+    let user: SlackUserId = user_id.into();
+
+    let conversation = session
+        .conversations_open(&SlackApiConversationsOpenRequest::new().with_users(vec![user.clone()]))
+        .await
+        .change_context(Error::Slack)?
+        .channel;
+
+    session
+        .chat_post_ephemeral(&SlackApiChatPostEphemeralRequest::new(
+            conversation.id,
+            user,
+            SlackMessageContent::new().with_text(text),
+        ))
+        .await
+        .change_context(Error::Slack)?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(view_state, client, user_state), fields(system_id))]
+pub async fn create_group(
+    view_state: SlackViewState,
+    client: &SlackHyperClient,
+    user_state: &State,
+    user_id: user::Id<Trusted>,
+) -> Result<(), Error> {
+    trace!("Creating group");
+    let data = group::View::try_from(view_state).change_context(Error::ParsingView)?;
+
+    let Some(system_id) = System::fetch_by_user_id(&user_id, &user_state.db)
+        .await
+        .attach_printable("Error checking if system exists")
+        .change_context(Error::Sqlx)?
+        .map(|system| system.id)
+    else {
+        bail!(Error::NoSystem);
+    };
+
+    fields!(system_id = %system_id);
+
+    validate_name(&data, system_id, None, user_state).await?;
+
+    let id = data
+        .add(system_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    confirm(
+        client,
+        user_id,
+        format!(
+            "Successfully added {}! Its ID is {}. Add members with `/groups add-member {} <members>`",
+            data.name, id, data.name
+        ),
+    )
+    .await
+}
+
+#[tracing::instrument(skip(view_state, client, user_state))]
+pub async fn edit_group(
+    view_state: SlackViewState,
+    client: &SlackHyperClient,
+    user_state: &State,
+    user_id: user::Id<Trusted>,
+    group_id: group::Id<Trusted>,
+) -> Result<(), Error> {
+    trace!("Editing group");
+    let data = group::View::try_from(view_state).change_context(Error::ParsingView)?;
+
+    let system_id = group_id
+        .fetch(&user_state.db)
+        .await
+        .change_context(Error::Sqlx)?
+        .system_id;
+
+    validate_name(&data, system_id, Some(group_id), user_state).await?;
+
+    data.update(group_id, &user_state.db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    confirm(
+        client,
+        user_id,
+        format!("Successfully edited {} (ID {})", data.name, group_id),
+    )
+    .await
+}
@@ -3,7 +3,7 @@ use slack_morphism::prelude::*;
 use tracing::trace;
 
 use crate::{
-    BOT_TOKEN, fields,
+    BOT_TOKEN, avatar, fields,
     models::{
         member,
         system::System,
@@ -22,18 +22,27 @@ pub enum Error {
     ParsingView,
     /// No system found for the user
     NoSystem,
+    /// {0}
+    InvalidProfile(member::ProfileError),
+    /// Couldn't set the uploaded profile picture
+    Avatar,
 }
 
-#[tracing::instrument(skip(view_state, client, user_state), fields(system_id))]
+#[tracing::instrument(skip(view_state, client, user_state, avatar), fields(system_id))]
 pub async fn create_member(
     view_state: SlackViewState,
     client: &SlackHyperClient,
     user_state: &State,
     user_id: user::Id<Trusted>,
+    avatar: Option<SlackFile>,
 ) -> Result<(), Error> {
     trace!("Creating member");
     let data = member::View::try_from(view_state).change_context(Error::ParsingView)?;
 
+    if let Err(error) = data.validate() {
+        bail!(Error::InvalidProfile(error));
+    }
+
     let Some(system_id) = System::fetch_by_user_id(&user_id, &user_state.db)
         .await
         .attach_printable("Error checking if system exists")
@@ -50,6 +59,12 @@ pub async fn create_member(
         .await
         .change_context(Error::Sqlx)?;
 
+    if let Some(file) = avatar {
+        avatar::set_avatar(&file, id, &user_state.db)
+            .await
+            .change_context(Error::Avatar)?;
+    }
+
     let session = client.open_session(&BOT_TOKEN);
     let user: SlackUserId = user_id.into();
 
@@ -74,21 +89,33 @@ pub async fn create_member(
     Ok(())
 }
 
-#[tracing::instrument(skip(view_state, client, user_state))]
+#[tracing::instrument(skip(view_state, client, user_state, avatar))]
 pub async fn edit_member(
     view_state: SlackViewState,
     client: &SlackHyperClient,
     user_state: &State,
     user_id: user::Id<Trusted>,
     member_id: member::Id<Trusted>,
+    avatar: Option<SlackFile>,
 ) -> Result<(), Error> {
     trace!("Editing member");
     let data = member::View::try_from(view_state).change_context(Error::ParsingView)?;
 
+    if let Err(error) = data.validate() {
+        bail!(Error::InvalidProfile(error));
+    }
+
     data.update(member_id, &user_state.db)
         .await
         .change_context(Error::Sqlx)?;
 
+    // Set after the update, so it replaces whatever URL was in the modal
+    if let Some(file) = avatar {
+        avatar::set_avatar(&file, member_id, &user_state.db)
+            .await
+            .change_context(Error::Avatar)?;
+    }
+
     let session = client.open_session(&BOT_TOKEN);
     let user: SlackUserId = user_id.into();
 
@@ -3,6 +3,7 @@ use std::sync::Arc;
 use tracing::{debug, warn};
 
 use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
 
 use crate::{
     BOT_TOKEN, fields,
@@ -418,7 +419,7 @@ pub async fn reproxy(
 
     let message_request =
         SlackApiChatPostMessageRequest::new(channel_id.clone(), message.content.clone())
-            .with_username(member.display_name.clone())
+            .with_username(system.proxy_username(&member.display_name, member.show_system_tag))
             .opt_icon_url(member.profile_picture_url.clone());
 
     session
@@ -635,7 +636,7 @@ pub async fn info(
         return Ok(());
     };
 
-    let member = log
+    let mut member = log
         .member_id
         .fetch(&user_state.db)
         .await
@@ -647,7 +648,27 @@ pub async fn info(
         .await
         .change_context(Error::Sqlx)?;
 
-    let blocks = slack_blocks![
+    let is_owner = *system.owner_id == event.user.id;
+
+    if !is_owner {
+        member.hide_private_info();
+    }
+
+    let fronting_members = visible_front(&system, is_owner, &user_state.db).await?;
+
+    let custom_fields = if member.custom_fields_visible(is_owner) {
+        member
+            .id
+            .custom_fields(&user_state.db)
+            .await
+            .change_context(Error::Sqlx)?
+    } else {
+        Vec::new()
+    };
+
+    let profile = member::profile_blocks(&member, &custom_fields);
+
+    let mut blocks = slack_blocks![
         some_into(SlackHeaderBlock::new(member.full_name.into())),
         some_into(SlackDividerBlock::new()),
         some_into(
@@ -669,10 +690,24 @@ pub async fn info(
                     ))
                 )))
         ),
-        optionally_into(system.currently_fronting_member_id.is_some_and(|id| id == member.id) => SlackSectionBlock::new().with_text(md!("*Fronting*")))
-        // TO-DO: fields
+        optionally_into(fronting_members.iter().flatten().any(|fronter| fronter.id == member.id) => SlackSectionBlock::new().with_text(md!("*Fronting*")))
     ];
 
+    blocks.extend(profile);
+
+    if let Some(fronting_members) = fronting_members {
+        blocks.push(
+            SlackContextBlock::new(vec![SlackContextBlockElement::MarkDown(
+                SlackBlockMarkDownText::new(format!(
+                    "Currently fronting: {}",
+                    member::describe_front(&fronting_members)
+                        .unwrap_or_else(|| "Nobody".to_string())
+                )),
+            )])
+            .into(),
+        );
+    }
+
     session
         .chat_post_ephemeral(&SlackApiChatPostEphemeralRequest::new(
             event.channel.unwrap().id,
@@ -686,3 +721,25 @@ pub async fn info(
 
     Ok(())
 }
+
+/// Who's fronting in the system, leaving out private members, or `None` if the front is private to someone else
+async fn visible_front(
+    system: &System,
+    is_owner: bool,
+    db: &SqlitePool,
+) -> Result<Option<Vec<Member>>, Error> {
+    if !system.front_privacy.is_visible(is_owner) {
+        return Ok(None);
+    }
+
+    let mut fronting_members = system
+        .id
+        .fronting_members(db)
+        .await
+        .change_context(Error::Sqlx)?;
+
+    // Private members aren't shown as fronting either, like in the member list
+    fronting_members.retain(|member| member.privacy.is_visible(is_owner));
+
+    Ok(Some(fronting_members))
+}
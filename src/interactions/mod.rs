@@ -1,3 +1,5 @@
+pub mod check_in;
+mod group;
 mod member;
 mod message;
 use std::error::Error;
@@ -9,31 +11,44 @@ use member::{create_member, edit_member};
 use slack_morphism::prelude::*;
 use tracing::{debug, error, warn};
 
-use crate::models::{self, trust::Trusted, user};
-use crate::{BOT_TOKEN, fields};
+use crate::models::{
+    self,
+    trust::{Trusted, Untrusted},
+    user,
+};
+use crate::{BOT_TOKEN, avatar::ModalAvatar, fields};
 
-#[tracing::instrument(skip(event, environment))]
+#[tracing::instrument(skip(event, environment, avatar))]
 pub async fn process_interaction_event(
     Extension(environment): Extension<Arc<SlackHyperListenerEnvironment>>,
     Extension(event): Extension<SlackInteractionEvent>,
+    Extension(avatar): Extension<ModalAvatar>,
 ) {
     let client = environment.client.clone();
     let states = environment.user_state.clone();
 
-    if let Err(error) = interaction_event(client, event, states).await {
+    // https://rust-lang.github.io/rust-clippy/master/index.html#large_futures
+    if let Err(error) = Box::pin(interaction_event(client, event, states, avatar)).await {
         error!(?error, "Error processing interaction event");
     }
 }
 
-#[tracing::instrument(skip(client, event, states))]
+#[tracing::instrument(skip(client, event, states, avatar))]
 async fn interaction_event(
     client: Arc<SlackHyperClient>,
     event: SlackInteractionEvent,
     states: SlackClientEventsUserState,
+    avatar: ModalAvatar,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     match event {
         SlackInteractionEvent::ViewSubmission(slack_interaction_view_submission_event) => {
-            handle_view_submission(slack_interaction_view_submission_event, client, states).await
+            handle_view_submission(
+                slack_interaction_view_submission_event,
+                client,
+                states,
+                avatar,
+            )
+            .await
         }
         SlackInteractionEvent::MessageAction(message_event) => {
             debug!(?message_event, "Received message action event");
@@ -74,6 +89,26 @@ async fn interaction_event(
             }
             Ok(())
         }
+        SlackInteractionEvent::BlockActions(block_event) => {
+            debug!(?block_event, "Received block actions event");
+
+            let is_check_in = block_event
+                .actions
+                .iter()
+                .flatten()
+                .any(|action| check_in::is_check_in_action(&action.action_id));
+
+            if is_check_in {
+                let states = states.read().await;
+                let user_state = states.get_user_state::<user::State>().unwrap();
+
+                check_in::handle_action(block_event, &client, &user_state.db).await?;
+            } else {
+                debug!("Block actions event isn't from a check-in");
+            }
+
+            Ok(())
+        }
         event => {
             debug!(?event, "Received interaction event",);
             Ok(())
@@ -85,6 +120,7 @@ async fn handle_view_submission(
     view_submission: SlackInteractionViewSubmissionEvent,
     client: Arc<SlackHyperClient>,
     states: SlackClientEventsUserState,
+    avatar: ModalAvatar,
 ) -> Result<(), Box<dyn Error + Send + Sync>> {
     match view_submission.view.view {
         SlackView::Home(view) => {
@@ -101,20 +137,21 @@ async fn handle_view_submission(
                 return Ok(());
             };
 
-            handle_modal_view(client, view, view_state, states, user_id).await;
+            handle_modal_view(client, view, view_state, states, user_id, avatar.0).await;
 
             Ok(())
         }
     }
 }
 
-#[tracing::instrument(skip(client, view, states))]
+#[tracing::instrument(skip(client, view, states, avatar))]
 async fn handle_modal_view(
     client: Arc<SlackHyperClient>,
     view: SlackModalView,
     view_state: SlackViewState,
     states: SlackClientEventsUserState,
     user_id: user::Id<Trusted>,
+    avatar: Option<SlackFile>,
 ) {
     let states = states.read().await;
     let user_state = states.get_user_state::<user::State>().unwrap();
@@ -132,7 +169,7 @@ async fn handle_modal_view(
             debug!("Received create member modal view");
 
             if let Err(error) =
-                create_member(view_state, &client, user_state, user_id.clone()).await
+                create_member(view_state, &client, user_state, user_id.clone(), avatar).await
             {
                 handle_user_error(error, user_id.into(), client).await;
             }
@@ -182,43 +219,10 @@ async fn handle_modal_view(
             }
         }
         Some(id) if id.starts_with("edit_member_") => {
-            debug!("Received edit member modal view");
-
-            let Ok(member_id) = id
-                .strip_prefix("edit_member_")
-                .expect("id starts with edit_member_")
-                .parse::<i64>()
-                .map(models::member::Id::new)
-            else {
-                error!(
-                    id,
-                    "Failed to parse member id from external id. Bailing in case this was a malicious call",
-                );
-                return;
-            };
-
-            // TO-DO: better handling of Err case
-            let Ok(Some(trusted_member_id)) =
-                member_id.validate_by_user(&user_id, &user_state.db).await
-            else {
-                error!(
-                    id,
-                    "Failed to validate member id from external id. Bailing in case this was a malicious call",
-                );
-                return;
-            };
-
-            if let Err(error) = edit_member(
-                view_state,
-                &client,
-                user_state,
-                user_id.clone(),
-                trusted_member_id,
-            )
-            .await
-            {
-                handle_user_error(error, user_id.into(), client).await;
-            }
+            handle_edit_member_view(client, id, view_state, user_state, user_id, avatar).await;
+        }
+        Some(id) if id == "create_group" || id.starts_with("edit_group_") => {
+            handle_group_view(client, id, view_state, user_state, user_id).await;
         }
         Some(id) => {
             error!("receieved unknown external id: {id}");
@@ -226,6 +230,105 @@ async fn handle_modal_view(
     }
 }
 
+/// Handles the member edit modal (`edit_member_<id>`)
+#[tracing::instrument(skip(client, view_state, user_state, avatar))]
+async fn handle_edit_member_view(
+    client: Arc<SlackHyperClient>,
+    id: &str,
+    view_state: SlackViewState,
+    user_state: &user::State,
+    user_id: user::Id<Trusted>,
+    avatar: Option<SlackFile>,
+) {
+    debug!("Received edit member modal view");
+
+    let Ok(member_id) = id
+        .strip_prefix("edit_member_")
+        .expect("id starts with edit_member_")
+        .parse::<i64>()
+        .map(models::member::Id::new)
+    else {
+        error!(
+            id,
+            "Failed to parse member id from external id. Bailing in case this was a malicious call",
+        );
+        return;
+    };
+
+    // TO-DO: better handling of Err case
+    let Ok(Some(trusted_member_id)) = member_id.validate_by_user(&user_id, &user_state.db).await
+    else {
+        error!(
+            id,
+            "Failed to validate member id from external id. Bailing in case this was a malicious call",
+        );
+        return;
+    };
+
+    if let Err(error) = edit_member(
+        view_state,
+        &client,
+        user_state,
+        user_id.clone(),
+        trusted_member_id,
+        avatar,
+    )
+    .await
+    {
+        handle_user_error(error, user_id.into(), client).await;
+    }
+}
+
+/// Handles the group add (`create_group`) and edit (`edit_group_<id>`) modals
+#[tracing::instrument(skip(client, view_state, user_state))]
+async fn handle_group_view(
+    client: Arc<SlackHyperClient>,
+    id: &str,
+    view_state: SlackViewState,
+    user_state: &user::State,
+    user_id: user::Id<Trusted>,
+) {
+    debug!("Received group modal view");
+
+    let Some(group_id) = id.strip_prefix("edit_group_") else {
+        if let Err(error) =
+            group::create_group(view_state, &client, user_state, user_id.clone()).await
+        {
+            handle_user_error(error, user_id.into(), client).await;
+        }
+        return;
+    };
+
+    let Ok(group_id) = group_id.parse::<models::group::Id<Untrusted>>() else {
+        error!(
+            id,
+            "Failed to parse group id from external id. Bailing in case this was a malicious call",
+        );
+        return;
+    };
+
+    let Ok(Some(trusted_group_id)) = group_id.validate_by_user(&user_id, &user_state.db).await
+    else {
+        error!(
+            id,
+            "Failed to validate group id from external id. Bailing in case this was a malicious call",
+        );
+        return;
+    };
+
+    if let Err(error) = group::edit_group(
+        view_state,
+        &client,
+        user_state,
+        user_id.clone(),
+        trusted_group_id,
+    )
+    .await
+    {
+        handle_user_error(error, user_id.into(), client).await;
+    }
+}
+
 pub async fn handle_user_error<E>(
     error: Report<E>,
     user: SlackUserId,
@@ -2,12 +2,16 @@
 #![warn(clippy::pedantic, clippy::nursery, missing_docs, clippy::cargo)]
 #![allow(clippy::multiple_crate_versions)]
 
+mod announcements;
+mod avatar;
 mod commands;
 mod env;
 mod events;
 mod interactions;
 mod models;
 mod oauth;
+mod scheduler;
+mod status;
 mod util;
 
 use crate::models::{system, trust::Trusted, user};
@@ -129,6 +133,8 @@ async fn main() -> error_stack::Result<ExitCode, Error> {
 
     let state = user::State { db: pool.clone() };
 
+    tokio::spawn(scheduler::run(client.clone(), pool.clone()));
+
     let listener_environment: Arc<SlackHyperListenerEnvironment> = Arc::new(
         SlackClientEventsListenerEnvironment::new(client.clone()).with_user_state(state.clone()),
     );
@@ -141,6 +147,10 @@ async fn main() -> error_stack::Result<ExitCode, Error> {
     let app = axum::routing::Router::new()
         // Note: I do not use the slack-morphism oauth thing because it's a bit too much for me
         .route("/auth", axum::routing::get(oauth_handler))
+        .route(
+            "/avatar/{member_id}",
+            axum::routing::get(avatar::avatar_handler),
+        )
         .with_state(state.clone())
         .route(
             "/push",
@@ -160,11 +170,14 @@ async fn main() -> error_stack::Result<ExitCode, Error> {
         )
         .route(
             "/interaction",
-            axum::routing::post(process_interaction_event).layer(
-                listener
-                    .events_layer(&signing_secret)
-                    .with_event_extractor(SlackEventsExtractors::interaction_event()),
-            ),
+            axum::routing::post(process_interaction_event)
+                .layer(
+                    listener
+                        .events_layer(&signing_secret)
+                        .with_event_extractor(SlackEventsExtractors::interaction_event()),
+                )
+                // Runs before the listener, which drops uploaded files when parsing the event
+                .layer(axum::middleware::from_fn(avatar::capture_modal_avatar)),
         )
         .layer(
             TraceLayer::new_for_http().make_span_with(|request: &Request<_>| {
@@ -0,0 +1,262 @@
+use super::{
+    System, member,
+    system::{self, AutoproxyMode},
+    trust::Trusted,
+};
+use error_stack::{Result, ResultExt};
+use slack_morphism::{SlackChannelId, SlackTs};
+use sqlx::{SqlitePool, prelude::*};
+
+/// A system's autoproxy setting for one channel, used instead of [`System::autoproxy_mode`] there
+#[derive(FromRow, Debug)]
+#[allow(dead_code)]
+pub struct ChannelAutoproxy {
+    pub system_id: system::Id<Trusted>,
+    #[sqlx(try_from = "String")]
+    pub channel_id: SlackChannelId,
+    pub mode: AutoproxyMode,
+    /// The member messages are sent as in [`AutoproxyMode::Member`]
+    pub member_id: Option<member::Id<Trusted>>,
+    /// The member who last used a trigger in this channel, for [`AutoproxyMode::Latch`]
+    pub latch_member_id: Option<member::Id<Trusted>>,
+}
+
+impl ChannelAutoproxy {
+    /// The member a message without a trigger is sent as in this channel, if any
+    pub const fn member_id(&self, system: &System) -> Option<member::Id<Trusted>> {
+        match self.mode {
+            AutoproxyMode::Off => None,
+            AutoproxyMode::Front => system.currently_fronting_member_id,
+            AutoproxyMode::Latch => self.latch_member_id,
+            AutoproxyMode::Member => self.member_id,
+        }
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch(
+        system_id: system::Id<Trusted>,
+        channel_id: &SlackChannelId,
+        db: &SqlitePool,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            ChannelAutoproxy,
+            r#"
+            SELECT
+                system_id as "system_id: system::Id<Trusted>",
+                channel_id,
+                mode,
+                member_id as "member_id: member::Id<Trusted>",
+                latch_member_id as "latch_member_id: member::Id<Trusted>"
+            FROM channel_autoproxy
+            WHERE system_id = $1 AND channel_id = $2
+            "#,
+            system_id,
+            channel_id.0
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch channel autoproxy")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_by_system_id(
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            ChannelAutoproxy,
+            r#"
+            SELECT
+                system_id as "system_id: system::Id<Trusted>",
+                channel_id,
+                mode,
+                member_id as "member_id: member::Id<Trusted>",
+                latch_member_id as "latch_member_id: member::Id<Trusted>"
+            FROM channel_autoproxy
+            WHERE system_id = $1
+            "#,
+            system_id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch channel autoproxies")
+    }
+
+    /// Sets the autoproxy setting for a channel. Changing the mode forgets the channel's latched member
+    #[tracing::instrument(skip(db))]
+    pub async fn upsert(
+        system_id: system::Id<Trusted>,
+        channel_id: &SlackChannelId,
+        mode: AutoproxyMode,
+        member_id: Option<member::Id<Trusted>>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO channel_autoproxy (system_id, channel_id, mode, member_id)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (system_id, channel_id) DO UPDATE SET
+                latch_member_id = CASE WHEN mode = excluded.mode THEN latch_member_id END,
+                mode = excluded.mode,
+                member_id = excluded.member_id
+            "#,
+            system_id,
+            channel_id.0,
+            mode,
+            member_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to set channel autoproxy")
+        .map(|_| ())
+    }
+
+    /// Removes the autoproxy setting for a channel, so the system's setting is used there.
+    /// Returns whether there was one
+    #[tracing::instrument(skip(db))]
+    pub async fn delete(
+        system_id: system::Id<Trusted>,
+        channel_id: &SlackChannelId,
+        db: &SqlitePool,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM channel_autoproxy WHERE system_id = $1 AND channel_id = $2",
+            system_id,
+            channel_id.0
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to delete channel autoproxy")
+        .map(|result| result.rows_affected() > 0)
+    }
+
+    /// Records that a member used a trigger in this channel, for [`AutoproxyMode::Latch`]
+    #[tracing::instrument(skip(self, db), fields(system_id = %self.system_id, channel_id = %self.channel_id))]
+    pub async fn latch(
+        &mut self,
+        member_id: member::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        if self.mode != AutoproxyMode::Latch || self.latch_member_id == Some(member_id) {
+            return Ok(());
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE channel_autoproxy
+            SET latch_member_id = $3
+            WHERE system_id = $1 AND channel_id = $2
+            "#,
+            self.system_id,
+            self.channel_id.0,
+            member_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update channel latch member")?;
+
+        self.latch_member_id = Some(member_id);
+        Ok(())
+    }
+}
+
+/// The member who last used a trigger in a thread, who messages without a trigger in that thread are sent as.
+/// See [`System::thread_latch_timeout`]
+#[derive(FromRow, Debug)]
+#[allow(dead_code)]
+pub struct ThreadLatch {
+    pub system_id: system::Id<Trusted>,
+    #[sqlx(try_from = "String")]
+    pub channel_id: SlackChannelId,
+    #[sqlx(try_from = "String")]
+    pub thread_ts: SlackTs,
+    pub member_id: member::Id<Trusted>,
+    /// When the latch was last used
+    pub updated_at: time::PrimitiveDateTime,
+}
+
+impl ThreadLatch {
+    /// Whether the latch has gone unused for longer than `timeout`
+    pub fn is_expired(&self, timeout: time::Duration) -> bool {
+        time::OffsetDateTime::now_utc() - self.updated_at.assume_utc() > timeout
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch(
+        system_id: system::Id<Trusted>,
+        channel_id: &SlackChannelId,
+        thread_ts: &SlackTs,
+        db: &SqlitePool,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            ThreadLatch,
+            r#"
+            SELECT
+                system_id as "system_id: system::Id<Trusted>",
+                channel_id,
+                thread_ts,
+                member_id as "member_id: member::Id<Trusted>",
+                updated_at as "updated_at: time::PrimitiveDateTime"
+            FROM thread_latches
+            WHERE system_id = $1 AND channel_id = $2 AND thread_ts = $3
+            "#,
+            system_id,
+            channel_id.0,
+            thread_ts.0
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch thread latch")
+    }
+
+    /// Latches a thread onto a member, or keeps an existing latch from expiring
+    #[tracing::instrument(skip(db))]
+    pub async fn upsert(
+        system_id: system::Id<Trusted>,
+        channel_id: &SlackChannelId,
+        thread_ts: &SlackTs,
+        member_id: member::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO thread_latches (system_id, channel_id, thread_ts, member_id)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (system_id, channel_id, thread_ts) DO UPDATE SET
+                member_id = excluded.member_id,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            system_id,
+            channel_id.0,
+            thread_ts.0,
+            member_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to set thread latch")
+        .map(|_| ())
+    }
+
+    /// Deletes a system's latches that have gone unused for longer than `timeout`
+    #[tracing::instrument(skip(db))]
+    pub async fn delete_expired(
+        system_id: system::Id<Trusted>,
+        timeout: time::Duration,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        let timeout = timeout.whole_seconds();
+
+        sqlx::query!(
+            r#"
+            DELETE FROM thread_latches
+            WHERE system_id = $1 AND unixepoch(updated_at) < unixepoch('now') - $2
+            "#,
+            system_id,
+            timeout
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to delete expired thread latches")
+        .map(|_| ())
+    }
+}
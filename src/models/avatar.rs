@@ -0,0 +1,133 @@
+use super::{member, system, trust::Trusted};
+use error_stack::{Result, ResultExt};
+use slack_morphism::{SlackChannelId, SlackTs};
+use sqlx::{SqlitePool, prelude::*};
+
+/// An avatar uploaded from a Slack file. See [`crate::avatar`]
+#[derive(FromRow, Debug)]
+pub struct Avatar {
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+impl Avatar {
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch(member_id: i64, db: &SqlitePool) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Avatar,
+            r#"
+            SELECT content_type, data
+            FROM member_avatars
+            JOIN members ON members.id = member_avatars.member_id
+            WHERE member_id = $1 AND members.enabled = TRUE
+            "#,
+            member_id
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch avatar")
+    }
+
+    /// Saves the member's avatar and points their profile picture at it
+    #[tracing::instrument(skip(data, db))]
+    pub async fn save(
+        member_id: member::Id<Trusted>,
+        slack_file_id: &str,
+        content_type: &str,
+        data: &[u8],
+        profile_picture_url: &str,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO member_avatars (member_id, slack_file_id, content_type, data)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (member_id) DO UPDATE SET
+                slack_file_id = excluded.slack_file_id,
+                content_type = excluded.content_type,
+                data = excluded.data,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+            member_id,
+            slack_file_id,
+            content_type,
+            data
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to save avatar")?;
+
+        sqlx::query!(
+            "UPDATE members SET profile_picture_url = $1 WHERE id = $2",
+            profile_picture_url,
+            member_id
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to update member profile picture")?;
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit transaction")
+    }
+}
+
+/// A DM asking the owner to reply with an image for a member's avatar
+pub struct AvatarRequest;
+
+impl AvatarRequest {
+    #[tracing::instrument(skip(db))]
+    pub async fn create(
+        channel_id: &SlackChannelId,
+        message_ts: &SlackTs,
+        member_id: member::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "INSERT INTO avatar_requests (channel_id, message_ts, member_id) VALUES ($1, $2, $3)",
+            channel_id.0,
+            message_ts.0,
+            member_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to create avatar request")
+        .map(|_| ())
+    }
+
+    /// The member whose avatar a thread is for, if it's an avatar request for one of the system's enabled members
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_member_id(
+        channel_id: &SlackChannelId,
+        thread_ts: &SlackTs,
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Option<member::Id<Trusted>>, sqlx::Error> {
+        sqlx::query!(
+            r#"
+            SELECT
+                members.id as "id: member::Id<Trusted>"
+            FROM avatar_requests
+            JOIN members ON members.id = avatar_requests.member_id
+            WHERE
+                avatar_requests.channel_id = $1 AND
+                avatar_requests.message_ts = $2 AND
+                members.system_id = $3 AND
+                members.enabled = TRUE
+            "#,
+            channel_id.0,
+            thread_ts.0,
+            system_id
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch avatar request")
+        .map(|record| record.map(|record| record.id))
+    }
+}
@@ -0,0 +1,80 @@
+use super::{system, trust::Trusted};
+use error_stack::{Result, ResultExt};
+use sqlx::{SqliteConnection, SqlitePool, prelude::*};
+
+/// A pending check-in reminder, asking the owner whether the front is still right.
+/// Sent by the [`crate::scheduler`] once the front has stayed the same for the system's check-in interval.
+///
+/// A system has at most one. Any switch reschedules it (see [`system::Id::switch_front`])
+#[derive(FromRow, Debug)]
+#[allow(dead_code)]
+pub struct ScheduledCheckIn {
+    pub system_id: system::Id<Trusted>,
+    pub remind_at: time::PrimitiveDateTime,
+}
+
+impl ScheduledCheckIn {
+    /// Fetches every reminder that's due, oldest first
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_due(db: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            ScheduledCheckIn,
+            r#"
+            SELECT
+                system_id as "system_id: system::Id<Trusted>",
+                remind_at as "remind_at: time::PrimitiveDateTime"
+            FROM scheduled_check_ins
+            WHERE remind_at <= CURRENT_TIMESTAMP
+            ORDER BY remind_at
+            "#
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch due check-ins")
+    }
+
+    /// (Re)schedules the reminder for one check-in interval from now, if the system has one and anyone is fronting.
+    /// Otherwise, removes it
+    #[tracing::instrument(skip(db))]
+    pub async fn schedule(
+        system_id: system::Id<Trusted>,
+        db: &mut SqliteConnection,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM scheduled_check_ins WHERE system_id = $1",
+            system_id
+        )
+        .execute(&mut *db)
+        .await
+        .attach_printable("Failed to clear check-in")?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO scheduled_check_ins (system_id, remind_at)
+            SELECT id, datetime('now', '+' || check_in_interval || ' seconds')
+            FROM systems
+            WHERE id = $1 AND check_in_interval IS NOT NULL AND currently_fronting_member_id IS NOT NULL
+            "#,
+            system_id
+        )
+        .execute(&mut *db)
+        .await
+        .attach_printable("Failed to schedule check-in")
+        .map(|_| ())
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn delete(
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM scheduled_check_ins WHERE system_id = $1",
+            system_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to delete check-in")
+        .map(|_| ())
+    }
+}
@@ -0,0 +1,462 @@
+use std::{convert::Infallible, str::FromStr};
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::{SqlitePool, prelude::*, sqlite::SqliteQueryResult};
+use tracing::{debug, warn};
+
+use crate::id;
+
+use super::{
+    Member, member, system,
+    trust::{Trusted, Untrusted},
+    user,
+};
+
+id!(
+    /// For an ID to be trusted, it must
+    ///
+    /// - Be a valid ID in the database
+    /// - Be associated with a trusted system
+    => Group
+);
+
+impl Id<Untrusted> {
+    #[tracing::instrument(skip(db))]
+    pub async fn validate_by_system(
+        self,
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Option<Id<Trusted>>, sqlx::Error> {
+        sqlx::query!(
+            "SELECT
+                id as 'id: Id<Trusted>'
+            FROM groups
+            WHERE id = $1 AND system_id = $2",
+            self.id,
+            system_id.id
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to validate group by system")
+        .map(|res| res.map(|res| res.id))
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn validate_by_user(
+        self,
+        user_id: &user::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Option<Id<Trusted>>, sqlx::Error> {
+        sqlx::query!(
+            "
+                SELECT
+                    groups.id as 'id: Id<Trusted>'
+                FROM groups
+                JOIN systems ON groups.system_id = systems.id
+                WHERE groups.id = $1 AND systems.owner_id = $2
+            ",
+            self.id,
+            user_id
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to validate group by user")
+        .map(|res| res.map(|res| res.id))
+    }
+}
+
+impl Id<Trusted> {
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_by_name(
+        name: &str,
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query!(
+            "SELECT
+                id as 'id: Id<Trusted>'
+            FROM groups
+            WHERE name = $1 AND system_id = $2",
+            name,
+            system_id.id
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch group by name")
+        .map(|res| res.map(|res| res.id))
+    }
+
+    pub async fn fetch(self, db: &SqlitePool) -> Result<Group, sqlx::Error> {
+        Group::fetch_by_id(self, db).await
+    }
+
+    /// Adds a member to the group. Returns whether they weren't already in it
+    #[tracing::instrument(skip(db))]
+    pub async fn add_member(
+        self,
+        member_id: member::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query!(
+            "INSERT OR IGNORE INTO group_members (group_id, member_id) VALUES ($1, $2)",
+            self.id,
+            member_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to add member to group")
+        .map(|result| result.rows_affected() > 0)
+    }
+
+    /// Removes a member from the group. Returns whether they were in it
+    #[tracing::instrument(skip(db))]
+    pub async fn remove_member(
+        self,
+        member_id: member::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM group_members WHERE group_id = $1 AND member_id = $2",
+            self.id,
+            member_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to remove member from group")
+        .map(|result| result.rows_affected() > 0)
+    }
+
+    /// The group's members, including disabled ones, in the order they were added to the system
+    #[tracing::instrument(skip(db))]
+    pub async fn members(self, db: &SqlitePool) -> Result<Vec<Member>, sqlx::Error> {
+        sqlx::query_as!(
+            Member,
+            r#"
+            SELECT
+                members.id as "id: member::Id<Trusted>",
+                members.system_id as "system_id: system::Id<Trusted>",
+                members.full_name,
+                members.display_name,
+                members.profile_picture_url,
+                members.title,
+                members.pronouns,
+                members.name_pronunciation,
+                members.name_recording_url,
+                members.description,
+                members.color,
+                members.birthday,
+                members.enabled,
+                members.show_system_tag,
+                members.privacy,
+                members.name_privacy,
+                members.pronoun_privacy,
+                members.description_privacy,
+                members.birthday_privacy,
+                members.custom_fields_privacy,
+                members.created_at as "created_at: time::PrimitiveDateTime"
+            FROM
+                group_members
+            JOIN
+                members ON members.id = group_members.member_id
+            WHERE group_members.group_id = $1
+            ORDER BY members.id
+            "#,
+            self.id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch group members")
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A reference to a group, as given in a command
+pub enum GroupRef {
+    Id(Id<Untrusted>),
+    /// We were given the group's name
+    Name(String),
+}
+
+impl FromStr for GroupRef {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        s.parse::<i64>().map_or_else(
+            |_| Ok(Self::Name(s.to_string())),
+            |id| {
+                Ok(Self::Id(Id {
+                    id,
+                    trusted: std::marker::PhantomData,
+                }))
+            },
+        )
+    }
+}
+
+impl GroupRef {
+    #[tracing::instrument(skip(db))]
+    pub async fn validate_by_system(
+        &self,
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Option<Id<Trusted>>, sqlx::Error> {
+        match self {
+            Self::Id(id) => id
+                .validate_by_system(system_id, db)
+                .await
+                .attach_printable("Failed to validate group reference via id and system"),
+            Self::Name(name) => Id::fetch_by_name(name, system_id, db)
+                .await
+                .attach_printable("Failed to validate group reference via name and system"),
+        }
+    }
+}
+
+/// A named set of members in a system, like a subsystem
+#[derive(FromRow, Debug)]
+#[allow(dead_code)]
+pub struct Group {
+    pub id: Id<Trusted>,
+    pub system_id: system::Id<Trusted>,
+    /// Unique within the system, so it can be used to refer to the group in commands
+    pub name: String,
+    pub description: Option<String>,
+    pub icon_url: Option<String>,
+    pub created_at: time::PrimitiveDateTime,
+}
+
+/// A group, with how many members are in it. See [`Group::fetch_by_system_id`]
+#[derive(Debug)]
+pub struct GroupSummary {
+    pub group: Group,
+    pub member_count: i64,
+}
+
+impl Group {
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_by_id(group_id: Id<Trusted>, db: &SqlitePool) -> Result<Self, sqlx::Error> {
+        sqlx::query_as!(
+            Group,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>",
+                name,
+                description,
+                icon_url,
+                created_at as "created_at: time::PrimitiveDateTime"
+            FROM groups
+            WHERE id = $1
+            "#,
+            group_id.id
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to fetch group by id")
+    }
+
+    /// Fetches every group in a system, with their member counts, by name
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_by_system_id(
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Vec<GroupSummary>, sqlx::Error> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                groups.id as "id: Id<Trusted>",
+                groups.system_id as "system_id: system::Id<Trusted>",
+                groups.name,
+                groups.description,
+                groups.icon_url,
+                groups.created_at as "created_at: time::PrimitiveDateTime",
+                COUNT(group_members.member_id) as "member_count!: i64"
+            FROM
+                groups
+            LEFT JOIN
+                group_members ON group_members.group_id = groups.id
+            WHERE groups.system_id = $1
+            GROUP BY groups.id
+            ORDER BY groups.name
+            "#,
+            system_id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch groups")?;
+
+        Ok(records
+            .into_iter()
+            .map(|record| GroupSummary {
+                group: Self {
+                    id: record.id,
+                    system_id: record.system_id,
+                    name: record.name,
+                    description: record.description,
+                    icon_url: record.icon_url,
+                    created_at: record.created_at,
+                },
+                member_count: record.member_count,
+            })
+            .collect())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct View {
+    pub name: String,
+    pub description: Option<String>,
+    pub icon_url: Option<String>,
+}
+
+impl View {
+    /// Whether the name can be used to refer to the group in commands.
+    /// Commands are split on whitespace, and a number would be taken as a group ID
+    pub fn has_valid_name(&self) -> bool {
+        !self.name.contains(char::is_whitespace) && self.name.parse::<i64>().is_err()
+    }
+
+    /// Due to the way the slack blocks are created, all fields are moved.
+    /// Clone the whole struct if you need to keep the original.
+    pub fn create_blocks(self) -> Vec<SlackBlock> {
+        slack_blocks![
+            some_into(
+                SlackInputBlock::new(
+                    "Name".into(),
+                    SlackBlockPlainTextInputElement::new("name".into())
+                        .with_initial_value(self.name)
+                        .into(),
+                )
+                .with_hint(
+                    "Used to refer to the group in commands, so it can't contain spaces".into()
+                )
+            ),
+            some_into(
+                SlackInputBlock::new(
+                    "Description".into(),
+                    SlackBlockPlainTextInputElement::new("description".into())
+                        .with_multiline(true)
+                        .with_initial_value(self.description.unwrap_or_default())
+                        .into(),
+                )
+                .with_optional(true)
+            ),
+            some_into(
+                SlackInputBlock::new(
+                    "Icon URL".into(),
+                    SlackBlockPlainTextInputElement::new("icon_url".into())
+                        .with_initial_value(self.icon_url.unwrap_or_default())
+                        .into(),
+                )
+                .with_optional(true)
+            )
+        ]
+    }
+
+    pub fn create_add_view() -> SlackView {
+        SlackView::Modal(
+            SlackModalView::new("Add a new group".into(), Self::default().create_blocks())
+                .with_submit("Add".into())
+                .with_external_id("create_group".into()),
+        )
+    }
+
+    pub fn create_edit_view(self, group_id: Id<Trusted>) -> SlackView {
+        SlackView::Modal(
+            SlackModalView::new("Edit group".into(), self.create_blocks())
+                .with_submit("Edit".into())
+                .with_external_id(format!("edit_group_{}", group_id.id)),
+        )
+    }
+
+    /// Add a group to the database
+    ///
+    /// Returns the id of the new group
+    #[tracing::instrument(skip(db))]
+    pub async fn add(
+        &self,
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Id<Trusted>, sqlx::Error> {
+        debug!("Adding group {} to database", self.name);
+        sqlx::query!(
+            r#"
+            INSERT INTO groups (name, description, icon_url, system_id)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id as "id: Id<Trusted>"
+            "#,
+            self.name,
+            self.description,
+            self.icon_url,
+            system_id.id,
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Error adding group to database")
+        .map(|row| row.id)
+    }
+
+    /// Update a group in the database to match this view
+    #[tracing::instrument(skip(db))]
+    pub async fn update(
+        &self,
+        group_id: Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<SqliteQueryResult, sqlx::Error> {
+        sqlx::query!(
+            "UPDATE groups SET name = $1, description = $2, icon_url = $3 WHERE id = $4",
+            self.name,
+            self.description,
+            self.icon_url,
+            group_id,
+        )
+        .execute(db)
+        .await
+        .attach_printable("Error editing group in database")
+    }
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+/// A field was missing from the view
+pub struct MissingFieldError(String);
+
+impl TryFrom<SlackViewState> for View {
+    type Error = MissingFieldError;
+
+    fn try_from(value: SlackViewState) -> std::result::Result<Self, Self::Error> {
+        let mut view = Self::default();
+        for (_id, values) in value.values {
+            for (id, content) in values {
+                match &*id.0 {
+                    "name" => {
+                        view.name = content
+                            .value
+                            .ok_or_else(|| MissingFieldError("name".to_string()))?;
+                    }
+                    "description" => view.description = content.value,
+                    "icon_url" => view.icon_url = content.value,
+                    other => {
+                        warn!("Unknown field in view when parsing a group::View: {other}");
+                    }
+                }
+            }
+        }
+
+        if view.name.is_empty() {
+            return Err(MissingFieldError("name".to_string()));
+        }
+
+        Ok(view)
+    }
+}
+
+impl From<Group> for View {
+    fn from(value: Group) -> Self {
+        Self {
+            name: value.name,
+            description: value.description,
+            icon_url: value.icon_url,
+        }
+    }
+}
@@ -0,0 +1,246 @@
+//! In-memory trigger matching
+//!
+//! Every message goes through trigger detection, so each system's triggers are compiled once and kept
+//! in memory until they're changed. Anything that changes which triggers a system has (or which members
+//! they belong to are enabled) must call [`invalidate`].
+
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    ops::Range,
+    sync::{
+        Arc, LazyLock, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use error_stack::{Result, ResultExt};
+use regex::Regex;
+use slack_morphism::SlackChannelId;
+use sqlx::SqlitePool;
+use tracing::{debug, warn};
+
+use crate::util;
+
+use super::{
+    member, system,
+    trigger::{self, ChannelFilter, ChannelScope, MatchOptions, Type},
+    trust::Trusted,
+};
+
+/// Compiled matchers, keyed by system ID
+static MATCHERS: LazyLock<RwLock<HashMap<i64, Arc<Matcher>>>> = LazyLock::new(RwLock::default);
+
+/// Bumped on every invalidation, so a matcher built from data that changed mid-load is never cached
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Drops the cached matcher for a system, so it's rebuilt from the database on the next message
+pub fn invalidate(system_id: system::Id<Trusted>) {
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    MATCHERS
+        .write()
+        .expect("Matcher cache lock poisoned")
+        .remove(&system_id.id);
+}
+
+/// All triggers of a system's enabled members, ready to match against messages
+#[derive(Debug)]
+pub struct Matcher {
+    triggers: Vec<CompiledTrigger>,
+}
+
+#[derive(Debug)]
+struct CompiledTrigger {
+    id: trigger::Id<Trusted>,
+    member_id: member::Id<Trusted>,
+    /// The trigger text as stored
+    text: String,
+    /// The trigger text with Slack's escaping undone
+    unescaped: String,
+    typ: Type,
+    options: MatchOptions,
+    /// Only set for [`Type::Regex`]
+    regex: Option<Regex>,
+    channels: ChannelFilter,
+}
+
+/// A trigger that matched a message
+#[derive(Debug)]
+pub struct Match<'a> {
+    pub trigger_id: trigger::Id<Trusted>,
+    pub member_id: member::Id<Trusted>,
+    pub trigger_text: &'a str,
+    pub typ: Type,
+    /// Where the message content is in the (unescaped) message
+    pub content: Range<usize>,
+}
+
+impl Match<'_> {
+    /// How good of a match this is, compared to other matches on the same message. Higher is better
+    fn rank(&self, message_len: usize) -> (usize, u8, Reverse<i64>) {
+        (
+            message_len - self.content.len(),
+            precedence(self.typ),
+            Reverse(self.trigger_id.id),
+        )
+    }
+}
+
+impl Matcher {
+    /// Fetches the matcher for a system, building and caching it if needed
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch(
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Arc<Self>, sqlx::Error> {
+        let cached = MATCHERS
+            .read()
+            .expect("Matcher cache lock poisoned")
+            .get(&system_id.id)
+            .cloned();
+
+        if let Some(matcher) = cached {
+            return Ok(matcher);
+        }
+
+        debug!("Building trigger matcher");
+
+        let generation = GENERATION.load(Ordering::SeqCst);
+
+        let triggers = sqlx::query!(
+            r#"
+            SELECT
+                triggers.id as "id: trigger::Id<Trusted>",
+                triggers.member_id as "member_id: member::Id<Trusted>",
+                triggers.text,
+                triggers.typ as "typ: Type",
+                triggers.case_sensitive as "case_sensitive: bool",
+                triggers.ignore_whitespace as "ignore_whitespace: bool",
+                triggers.channel_scope as "channel_scope: ChannelScope"
+            FROM
+                triggers
+            JOIN
+                members ON members.id = triggers.member_id
+            WHERE
+                triggers.system_id = $1 AND
+                members.enabled = TRUE
+            "#,
+            system_id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch triggers for matcher")?;
+
+        let mut channels = trigger::Trigger::fetch_channels_by_system_id(system_id, db).await?;
+
+        let matcher = Arc::new(Self {
+            triggers: triggers
+                .into_iter()
+                .filter_map(|record| {
+                    let unescaped = util::unescape(&record.text);
+                    let options = MatchOptions {
+                        case_sensitive: record.case_sensitive,
+                        ignore_whitespace: record.ignore_whitespace,
+                    };
+
+                    let regex = if record.typ == Type::Regex {
+                        match Type::regex(&unescaped, options) {
+                            Ok(regex) => Some(regex),
+                            Err(error) => {
+                                warn!(?error, trigger_id = %record.id, "Skipping invalid regex trigger");
+                                return None;
+                            }
+                        }
+                    } else {
+                        None
+                    };
+
+                    Some(CompiledTrigger {
+                        id: record.id,
+                        member_id: record.member_id,
+                        text: record.text,
+                        unescaped,
+                        typ: record.typ,
+                        options,
+                        regex,
+                        channels: ChannelFilter {
+                            scope: record.channel_scope,
+                            channels: channels.remove(&record.id.id).unwrap_or_default(),
+                        },
+                    })
+                })
+                .collect(),
+        });
+
+        {
+            let mut matchers = MATCHERS.write().expect("Matcher cache lock poisoned");
+
+            // Checked under the lock, so a concurrent invalidation either skips this insert or removes it afterwards
+            if GENERATION.load(Ordering::SeqCst) == generation {
+                matchers.insert(system_id.id, matcher.clone());
+            }
+        }
+
+        Ok(matcher)
+    }
+
+    /// Finds the trigger that should proxy a message, if any.
+    ///
+    /// The trigger with the longest match wins. Ties are broken by type (paired, prefix, suffix, then regex),
+    /// then by whichever trigger was created first. The message is expected to already be unescaped.
+    /// Triggers that can't be used in `channel` are skipped.
+    pub fn find(&self, message: &str, channel: &SlackChannelId) -> Option<Match<'_>> {
+        self.candidates(message, channel)
+            .max_by_key(|candidate| candidate.rank(message.len()))
+    }
+
+    /// Finds every trigger that matches a message, with the one [`Self::find`] would pick first
+    pub fn find_all(&self, message: &str, channel: &SlackChannelId) -> Vec<Match<'_>> {
+        let mut matches: Vec<_> = self.candidates(message, channel).collect();
+        matches.sort_by_key(|candidate| Reverse(candidate.rank(message.len())));
+        matches
+    }
+
+    fn candidates<'a, 'm>(
+        &'a self,
+        message: &'m str,
+        channel: &'m SlackChannelId,
+    ) -> impl Iterator<Item = Match<'a>> + use<'a, 'm> {
+        self.triggers
+            .iter()
+            .filter(|trigger| trigger.channels.allows(channel))
+            .filter_map(|trigger| {
+                Some(Match {
+                    trigger_id: trigger.id,
+                    member_id: trigger.member_id,
+                    trigger_text: &trigger.text,
+                    typ: trigger.typ,
+                    content: trigger.content_range(message)?,
+                })
+            })
+    }
+}
+
+impl CompiledTrigger {
+    fn content_range(&self, message: &str) -> Option<Range<usize>> {
+        match &self.regex {
+            Some(regex) => regex
+                .captures(message)?
+                .name(trigger::REGEX_CONTENT_GROUP)
+                .map(|content| self.options.trim(message, content.range())),
+            None => self
+                .typ
+                .content_range(&self.unescaped, message, self.options),
+        }
+    }
+}
+
+/// How a trigger type ranks against others when two triggers match the same length
+const fn precedence(typ: Type) -> u8 {
+    match typ {
+        Type::Paired => 3,
+        Type::Prefix => 2,
+        Type::Suffix => 1,
+        Type::Regex => 0,
+    }
+}
@@ -1,15 +1,17 @@
-use std::{convert::Infallible, str::FromStr};
+use std::{convert::Infallible, ops::Range, str::FromStr};
 
 use error_stack::{Result, ResultExt};
 use slack_morphism::prelude::*;
-use sqlx::{SqlitePool, prelude::*, sqlite::SqliteQueryResult};
+use sqlx::{SqliteConnection, SqlitePool, prelude::*};
 use tracing::{debug, warn};
 
-use crate::id;
+use crate::{avatar, id};
 
 use super::{
+    matcher,
+    privacy::Privacy,
     system,
-    trigger::{Trigger, Type},
+    trigger::Trigger,
     trust::{Trusted, Untrusted},
     user,
 };
@@ -114,19 +116,119 @@ impl Id<Trusted> {
             .map(|res| res.enabled)
     }
 
-    pub async fn set_enabled(
+    pub async fn set_enabled(self, enabled: bool, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        let system_id = sqlx::query!(
+            r#"
+            UPDATE members
+            SET enabled = $1
+            WHERE id = $2
+            RETURNING system_id as "system_id: system::Id<Trusted>"
+            "#,
+            enabled,
+            self
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Failed to update member enabled status")?
+        .system_id;
+
+        matcher::invalidate(system_id);
+
+        Ok(())
+    }
+
+    /// Sets whether the system tag is shown after the member's name on proxied messages
+    #[tracing::instrument(skip(db))]
+    pub async fn set_show_system_tag(self, show: bool, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE members SET show_system_tag = $1 WHERE id = $2",
+            show,
+            self
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update member system tag setting")
+        .map(|_| ())
+    }
+
+    /// Updates who can see the member and their info. Any setting left as `None` is unchanged
+    #[tracing::instrument(skip(db))]
+    pub async fn update_privacy(
         self,
-        enabled: bool,
+        changes: &PrivacyChanges,
         db: &SqlitePool,
-    ) -> Result<SqliteQueryResult, sqlx::Error> {
+    ) -> Result<(), sqlx::Error> {
         sqlx::query!(
-            "UPDATE members SET enabled = $1 WHERE id = $2",
-            enabled,
-            self
+            r#"
+            UPDATE members
+            SET
+                privacy = coalesce($2, privacy),
+                name_privacy = coalesce($3, name_privacy),
+                pronoun_privacy = coalesce($4, pronoun_privacy),
+                description_privacy = coalesce($5, description_privacy),
+                birthday_privacy = coalesce($6, birthday_privacy),
+                custom_fields_privacy = coalesce($7, custom_fields_privacy)
+            WHERE id = $1
+            "#,
+            self,
+            changes.member,
+            changes.name,
+            changes.pronouns,
+            changes.description,
+            changes.birthday,
+            changes.custom_fields
         )
         .execute(db)
         .await
-        .attach_printable("Failed to update member enabled status")
+        .attach_printable("Failed to update member privacy")
+        .map(|_| ())
+    }
+
+    /// The member's custom fields, in order
+    #[tracing::instrument(skip(db))]
+    pub async fn custom_fields(self, db: &SqlitePool) -> Result<Vec<CustomField>, sqlx::Error> {
+        sqlx::query_as!(
+            CustomField,
+            "SELECT name, value FROM member_custom_fields WHERE member_id = $1 ORDER BY position",
+            self
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch member custom fields")
+    }
+
+    /// Replaces the member's custom fields
+    #[tracing::instrument(skip(db))]
+    async fn set_custom_fields(
+        self,
+        custom_fields: &[CustomField],
+        db: &mut SqliteConnection,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM member_custom_fields WHERE member_id = $1",
+            self
+        )
+        .execute(&mut *db)
+        .await
+        .attach_printable("Failed to clear member custom fields")?;
+
+        for (position, field) in (0_i64..).zip(custom_fields) {
+            sqlx::query!(
+                r#"
+                INSERT INTO member_custom_fields (member_id, position, name, value)
+                VALUES ($1, $2, $3, $4)
+                "#,
+                self,
+                position,
+                field.name,
+                field.value
+            )
+            .execute(&mut *db)
+            .await
+            .attach_printable("Failed to insert member custom field")?;
+        }
+
+        Ok(())
     }
 }
 
@@ -185,9 +287,25 @@ pub struct Member {
     pub pronouns: Option<String>,
     pub name_pronunciation: Option<String>,
     pub name_recording_url: Option<String>,
+    /// Markdown shown on the member's profile
+    pub description: Option<String>,
+    /// Accent colour, as a hex code like `#ff8800`
+    pub color: Option<String>,
+    /// As `YYYY-MM-DD`, from Slack's date picker. See [`Member::format_birthday`]
+    pub birthday: Option<String>,
     pub created_at: time::PrimitiveDateTime,
     /// A deleted member is effectively a disabled member. They exist in the database, but you cannot interact with them in many ways.
     pub enabled: bool,
+    /// Whether the system tag is shown after the member's name on proxied messages. See [`system::System::tag`]
+    pub show_system_tag: bool,
+    /// Whether other users can see the member at all
+    pub privacy: Privacy,
+    pub name_privacy: Privacy,
+    pub pronoun_privacy: Privacy,
+    /// Covers the colour too
+    pub description_privacy: Privacy,
+    pub birthday_privacy: Privacy,
+    pub custom_fields_privacy: Privacy,
 }
 
 impl Member {
@@ -207,7 +325,17 @@ impl Member {
                 pronouns,
                 name_pronunciation,
                 name_recording_url,
+                description,
+                color,
+                birthday,
                 enabled,
+                show_system_tag,
+                privacy,
+                name_privacy,
+                pronoun_privacy,
+                description_privacy,
+                birthday_privacy,
+                custom_fields_privacy,
                 created_at as "created_at: time::PrimitiveDateTime"
             FROM members
             WHERE id = $1
@@ -218,10 +346,135 @@ impl Member {
         .await
         .attach_printable("Failed to fetch member by id")
     }
+
+    /// Removes the info other users can't see. The full name falls back to the display name,
+    /// and a private member keeps only what's on their messages anyway.
+    ///
+    /// Custom fields aren't part of a [`Member`], so check [`Member::custom_fields_visible`] before showing them
+    pub fn hide_private_info(&mut self) {
+        let hide_all = self.privacy == Privacy::Private;
+
+        if hide_all || self.name_privacy == Privacy::Private {
+            self.full_name.clone_from(&self.display_name);
+            self.name_pronunciation = None;
+        }
+
+        if hide_all || self.pronoun_privacy == Privacy::Private {
+            self.pronouns = None;
+        }
+
+        if hide_all || self.description_privacy == Privacy::Private {
+            self.description = None;
+            self.color = None;
+        }
+
+        if hide_all || self.birthday_privacy == Privacy::Private {
+            self.birthday = None;
+        }
+    }
+
+    /// Whether someone can see the member's custom fields, given whether they own the system
+    pub const fn custom_fields_visible(&self, is_owner: bool) -> bool {
+        self.privacy.is_visible(is_owner) && self.custom_fields_privacy.is_visible(is_owner)
+    }
+
+    /// The birthday written out, like "5 March 2000"
+    pub fn format_birthday(&self) -> Option<String> {
+        let mut parts = self.birthday.as_deref()?.splitn(3, '-');
+        let year = parts.next()?;
+        let month = time::Month::try_from(parts.next()?.parse::<u8>().ok()?).ok()?;
+        let day = parts.next()?.parse::<u8>().ok()?;
+
+        Some(format!("{day} {month} {year}"))
+    }
+}
+
+/// Changes to who can see a member and their info. Any setting left as `None` is unchanged
+#[derive(clap::Args, Debug, Default)]
+pub struct PrivacyChanges {
+    /// Whether other users can see the member at all, in member lists and message info
+    #[clap(long)]
+    pub member: Option<Privacy>,
+    /// Whether other users can see the member's full name. Their display name is shown on messages either way
+    #[clap(long)]
+    pub name: Option<Privacy>,
+    #[clap(long)]
+    pub pronouns: Option<Privacy>,
+    /// Whether other users can see the member's description and colour
+    #[clap(long)]
+    pub description: Option<Privacy>,
+    #[clap(long)]
+    pub birthday: Option<Privacy>,
+    #[clap(long)]
+    pub custom_fields: Option<Privacy>,
+}
+
+impl PrivacyChanges {
+    pub const fn is_empty(&self) -> bool {
+        self.member.is_none()
+            && self.name.is_none()
+            && self.pronouns.is_none()
+            && self.description.is_none()
+            && self.birthday.is_none()
+            && self.custom_fields.is_none()
+    }
+}
+
+/// A name/value pair shown on a member's profile
+#[derive(FromRow, Debug, Clone, PartialEq, Eq)]
+pub struct CustomField {
+    pub name: String,
+    pub value: String,
+}
+
+/// Blocks showing a member's description, birthday, colour and custom fields, for their profile.
+/// Empty if they have none of those
+pub fn profile_blocks(member: &Member, custom_fields: &[CustomField]) -> Vec<SlackBlock> {
+    let details = [
+        member
+            .format_birthday()
+            .map(|birthday| md!("*Birthday*\n{}", birthday)),
+        member
+            .color
+            .as_ref()
+            .map(|color| md!("*Colour*\n`{}`", color)),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+
+    let custom_fields = custom_fields
+        .iter()
+        .map(|field| md!("*{}*\n{}", field.name, field.value))
+        .collect::<Vec<_>>();
+
+    slack_blocks![
+        optionally_into(member.description.is_some() => SlackSectionBlock::new()
+            .with_text(md!("{}", member.description.clone().unwrap_or_default()))),
+        optionally_into(!details.is_empty() => SlackSectionBlock::new().with_fields(details)),
+        optionally_into(!custom_fields.is_empty() => SlackSectionBlock::new().with_fields(custom_fields))
+    ]
+}
+
+/// Lists everyone fronting by display name, marking the primary fronter if there's more than one member.
+///
+/// `members` should have the primary fronter first, as from [`system::Id::fronting_members`].
+/// Returns `None` if nobody is fronting.
+pub fn describe_front(members: &[Member]) -> Option<String> {
+    match members {
+        [] => None,
+        [member] => Some(member.display_name.clone()),
+        [primary, co_fronters @ ..] => Some(
+            std::iter::once(format!("{} (primary)", primary.display_name))
+                .chain(co_fronters.iter().map(|member| member.display_name.clone()))
+                .collect::<Vec<_>>()
+                .join(", "),
+        ),
+    }
 }
 
 /// All information required to display a member that has been detected from a message
-#[derive(FromRow, Debug)]
+#[derive(Debug)]
 pub struct DetectedMember {
     /// The ID of the member
     pub id: Id<Trusted>,
@@ -229,10 +482,11 @@ pub struct DetectedMember {
     pub display_name: String,
     /// Profile picture to use on messages
     pub profile_picture_url: Option<String>,
-    /// The trigger text that was matched
-    pub trigger_text: String,
-    /// The type of trigger
-    pub typ: Type,
+    /// Whether the system tag is shown after the member's name
+    pub show_system_tag: bool,
+    /// Where the message content is in the (unescaped) message, if a trigger matched it.
+    /// Everything outside of it is the trigger's tags
+    pub content: Option<Range<usize>>,
 }
 
 impl From<Member> for DetectedMember {
@@ -241,8 +495,8 @@ impl From<Member> for DetectedMember {
             id: value.id,
             display_name: value.display_name,
             profile_picture_url: value.profile_picture_url,
-            trigger_text: String::new(),
-            typ: Type::Prefix,
+            show_system_tag: value.show_system_tag,
+            content: None,
         }
     }
 }
@@ -256,13 +510,120 @@ pub struct View {
     pub pronouns: Option<String>,
     pub name_pronunciation: Option<String>,
     pub name_recording_url: Option<String>,
+    pub description: Option<String>,
+    pub color: Option<String>,
+    pub birthday: Option<String>,
+    pub custom_fields: Vec<CustomField>,
+}
+
+/// Slack won't show more than 3000 characters in a section's text
+pub const MAX_DESCRIPTION_LENGTH: usize = 3000;
+/// Slack won't show more than 10 fields in a section
+pub const MAX_CUSTOM_FIELDS: usize = 10;
+pub const MAX_CUSTOM_FIELD_NAME_LENGTH: usize = 100;
+/// Slack won't show more than 2000 characters in a field, which includes the name
+pub const MAX_CUSTOM_FIELD_VALUE_LENGTH: usize = 1800;
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum ProfileError {
+    /// The description can be at most 3000 characters long
+    DescriptionTooLong,
+    /// `{0}` isn't a colour. Use a hex code like #ff8800
+    InvalidColor(String),
+    /// A member can have at most 10 custom fields
+    TooManyCustomFields,
+    /// The custom field `{0}` needs a value. Write it like `Name: value`
+    MissingCustomFieldValue(String),
+    /// Custom field names can be at most 100 characters long
+    CustomFieldNameTooLong,
+    /// The custom field {0} can be at most 1800 characters long
+    CustomFieldValueTooLong(String),
+}
+
+/// Turns a hex colour like `#FF8800` or `f80` into `#ff8800`
+fn normalize_color(color: &str) -> Option<String> {
+    let hex = color.trim().trim_start_matches('#');
+
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    let hex = match hex.len() {
+        3 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 => hex.to_string(),
+        _ => return None,
+    };
+
+    Some(format!("#{}", hex.to_ascii_lowercase()))
+}
+
+/// Parses custom fields written one per line, as `Name: value`.
+/// A line without a value is kept with an empty one, so [`View::validate`] can point it out
+fn parse_custom_fields(text: &str) -> Vec<CustomField> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (name, value) = line.split_once(':').unwrap_or((line, ""));
+
+            CustomField {
+                name: name.trim().to_string(),
+                value: value.trim().to_string(),
+            }
+        })
+        .collect()
 }
 
 impl View {
+    /// Checks the profile fields fit in the blocks they're shown in
+    pub fn validate(&self) -> std::result::Result<(), ProfileError> {
+        if self
+            .description
+            .as_ref()
+            .is_some_and(|description| description.chars().count() > MAX_DESCRIPTION_LENGTH)
+        {
+            return Err(ProfileError::DescriptionTooLong);
+        }
+
+        if let Some(color) = self
+            .color
+            .as_ref()
+            .filter(|color| normalize_color(color).is_none())
+        {
+            return Err(ProfileError::InvalidColor(color.clone()));
+        }
+
+        if self.custom_fields.len() > MAX_CUSTOM_FIELDS {
+            return Err(ProfileError::TooManyCustomFields);
+        }
+
+        for field in &self.custom_fields {
+            if field.name.is_empty() || field.value.is_empty() {
+                return Err(ProfileError::MissingCustomFieldValue(field.name.clone()));
+            }
+
+            if field.name.chars().count() > MAX_CUSTOM_FIELD_NAME_LENGTH {
+                return Err(ProfileError::CustomFieldNameTooLong);
+            }
+
+            if field.value.chars().count() > MAX_CUSTOM_FIELD_VALUE_LENGTH {
+                return Err(ProfileError::CustomFieldValueTooLong(field.name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Due to the way the slack blocks are created, all fields are moved.
     /// Clone the whole struct if you need to keep the original.
     pub fn create_blocks(self) -> Vec<SlackBlock> {
-        slack_blocks![
+        let profile = Self::create_profile_blocks(
+            self.description,
+            self.color,
+            self.birthday,
+            &self.custom_fields,
+        );
+
+        let mut blocks = slack_blocks![
             // display info
             some_into(
                 SlackHeaderBlock::new("Display info".into()).with_block_id("display_info".into())
@@ -281,6 +642,18 @@ impl View {
                         .into(),
                 )
                 .with_optional(true)
+                .with_hint("Or upload an image below, or with `/members avatar`".into())
+            ),
+            some_into(
+                SlackInputBlock::new(
+                    "Profile picture".into(),
+                    SlackBlockFileInputElement::new(avatar::AVATAR_INPUT_ACTION_ID.into())
+                        .with_filetypes(["png", "jpg", "jpeg", "gif"].map(String::from).to_vec())
+                        .with_max_files(1)
+                        .into(),
+                )
+                .with_optional(true)
+                .with_hint("PNG, JPEG or GIF, up to 2 MB. Replaces the URL above".into())
             ),
             // personal info
             some_into(SlackDividerBlock::new()),
@@ -329,6 +702,69 @@ impl View {
                 )
                 .with_optional(true)
             )
+        ];
+
+        blocks.extend(profile);
+        blocks
+    }
+
+    fn create_profile_blocks(
+        description: Option<String>,
+        color: Option<String>,
+        birthday: Option<String>,
+        custom_fields: &[CustomField],
+    ) -> Vec<SlackBlock> {
+        slack_blocks![
+            // profile
+            some_into(SlackDividerBlock::new()),
+            some_into(SlackHeaderBlock::new("Profile".into()).with_block_id("profile".into())),
+            some_into(
+                SlackInputBlock::new(
+                    "Description".into(),
+                    SlackBlockPlainTextInputElement::new("description".into())
+                        .with_initial_value(description.unwrap_or_default())
+                        .with_multiline(true)
+                        .with_max_length(MAX_DESCRIPTION_LENGTH as u64)
+                        .into(),
+                )
+                .with_optional(true)
+            ),
+            some_into(
+                SlackInputBlock::new(
+                    "Colour".into(),
+                    SlackBlockPlainTextInputElement::new("color".into())
+                        .with_initial_value(color.unwrap_or_default())
+                        .with_placeholder("#ff8800".into())
+                        .into(),
+                )
+                .with_optional(true)
+            ),
+            some_into(
+                SlackInputBlock::new(
+                    "Birthday".into(),
+                    SlackBlockDatePickerElement::new("birthday".into())
+                        .opt_initial_date(birthday)
+                        .into(),
+                )
+                .with_optional(true)
+            ),
+            some_into(
+                SlackInputBlock::new(
+                    "Custom fields".into(),
+                    SlackBlockPlainTextInputElement::new("custom_fields".into())
+                        .with_initial_value(
+                            custom_fields
+                                .iter()
+                                .map(|field| format!("{}: {}", field.name, field.value))
+                                .collect::<Vec<_>>()
+                                .join("\n")
+                        )
+                        .with_multiline(true)
+                        .into(),
+                )
+                .with_optional(true)
+                .with_hint("One per line, like `Likes: tea`. Up to 10 fields".into())
+            )
         ]
     }
 
@@ -356,12 +792,17 @@ impl View {
         &self,
         system_id: system::Id<Trusted>,
         db: &SqlitePool,
-    ) -> error_stack::Result<i64, sqlx::Error> {
+    ) -> error_stack::Result<Id<Trusted>, sqlx::Error> {
         debug!("Adding member {} to database", self.display_name);
-        sqlx::query!("
-            INSERT INTO members (full_name, display_name, profile_picture_url, title, pronouns, name_pronunciation, name_recording_url, system_id)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-            RETURNING id
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+
+        let id = sqlx::query!("
+            INSERT INTO members (full_name, display_name, profile_picture_url, title, pronouns, name_pronunciation, name_recording_url, description, color, birthday, system_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+            RETURNING id as 'id: Id<Trusted>'
         ",
             self.full_name,
             self.display_name,
@@ -370,27 +811,43 @@ impl View {
             self.pronouns,
             self.name_pronunciation,
             self.name_recording_url,
+            self.description,
+            self.color,
+            self.birthday,
             system_id.id,
         )
-        .fetch_one(db)
+        .fetch_one(&mut *transaction)
         .await
-        .attach_printable("Error adding member to database")
-        .map(|row| row.id)
+        .attach_printable("Error adding member to database")?
+        .id;
+
+        id.set_custom_fields(&self.custom_fields, &mut transaction)
+            .await?;
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit transaction")?;
+
+        Ok(id)
     }
 
     /// Update a member in the database to match this view
-    ///
-    /// Returns None if the member does not exist
     #[tracing::instrument(skip(db))]
     pub async fn update(
         &self,
         member_id: Id<Trusted>,
         db: &SqlitePool,
-    ) -> error_stack::Result<SqliteQueryResult, sqlx::Error> {
+    ) -> error_stack::Result<(), sqlx::Error> {
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+
         sqlx::query!("
             UPDATE members
-            SET full_name = $1, display_name = $2, profile_picture_url = $3, title = $4, pronouns = $5, name_pronunciation = $6, name_recording_url = $7
-            WHERE id = $8
+            SET full_name = $1, display_name = $2, profile_picture_url = $3, title = $4, pronouns = $5, name_pronunciation = $6, name_recording_url = $7, description = $8, color = $9, birthday = $10
+            WHERE id = $11
         ",
             self.full_name,
             self.display_name,
@@ -399,9 +856,21 @@ impl View {
             self.pronouns,
             self.name_pronunciation,
             self.name_recording_url,
+            self.description,
+            self.color,
+            self.birthday,
             member_id,
-        ).execute(db).await
-        .attach_printable("Error editing member in database")
+        ).execute(&mut *transaction).await
+        .attach_printable("Error editing member in database")?;
+
+        member_id
+            .set_custom_fields(&self.custom_fields, &mut transaction)
+            .await?;
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit transaction")
     }
 }
 
@@ -428,10 +897,27 @@ impl TryFrom<SlackViewState> for View {
                             .ok_or_else(|| MissingFieldError("display_name".to_string()))?;
                     }
                     "profile_picture_url" => view.profile_picture_url = content.value,
+                    // The uploaded file isn't in the view state, see `avatar::capture_modal_avatar`
+                    avatar::AVATAR_INPUT_ACTION_ID => {}
                     "title" => view.title = content.value,
                     "pronouns" => view.pronouns = content.value,
                     "name_pronunciation" => view.name_pronunciation = content.value,
                     "name_recording_url" => view.name_recording_url = content.value,
+                    "description" => view.description = content.value,
+                    "color" => {
+                        // Keep what was typed if it isn't a colour, so validation can say so
+                        view.color = content
+                            .value
+                            .map(|color| normalize_color(&color).unwrap_or(color));
+                    }
+                    "birthday" => view.birthday = content.selected_date,
+                    "custom_fields" => {
+                        view.custom_fields = content
+                            .value
+                            .as_deref()
+                            .map(parse_custom_fields)
+                            .unwrap_or_default();
+                    }
                     other => {
                         warn!("Unknown field in view when parsing a member::View: {other}");
                     }
@@ -461,6 +947,10 @@ impl From<Member> for View {
             pronouns: value.pronouns,
             name_pronunciation: value.name_pronunciation,
             name_recording_url: value.name_recording_url,
+            description: value.description,
+            color: value.color,
+            birthday: value.birthday,
+            custom_fields: Vec::new(),
         }
     }
 }
@@ -1,13 +1,24 @@
 pub mod alias;
+pub mod autoproxy;
+pub mod avatar;
+pub mod check_in;
+pub mod group;
+pub mod matcher;
 pub mod member;
 pub mod message;
+pub mod privacy;
+pub mod revert;
+pub mod saved_status;
+pub mod switch;
 pub mod system;
 pub mod trigger;
 pub mod trust;
 pub mod user;
 
 pub use alias::Alias;
+pub use group::Group;
 pub use member::{DetectedMember, Member};
 pub use message::MessageLog;
+pub use switch::Switch;
 pub use system::System;
 pub use trigger::Trigger;
@@ -0,0 +1,36 @@
+#[derive(
+    Debug, sqlx::Type, displaydoc::Display, PartialEq, Eq, clap::ValueEnum, Clone, Copy, Default,
+)]
+#[repr(i64)]
+/// Who can see part of a member or system
+#[ignore_extra_doc_attributes]
+pub enum Privacy {
+    /// Public
+    ///
+    /// Anyone in the workspace can see it
+    #[default]
+    Public = 0,
+    /// Private
+    ///
+    /// Only the system's owner can see it
+    Private = 1,
+}
+
+impl From<i64> for Privacy {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::Public,
+            1 => Self::Private,
+            _ => unreachable!(
+                "Invalid privacy value. This means the database and rust struct are out of sync"
+            ),
+        }
+    }
+}
+
+impl Privacy {
+    /// Whether someone can see it, given whether they own the system
+    pub const fn is_visible(self, is_owner: bool) -> bool {
+        is_owner || matches!(self, Self::Public)
+    }
+}
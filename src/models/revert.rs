@@ -0,0 +1,144 @@
+use super::{switch, system, trust::Trusted};
+use error_stack::{Result, ResultExt};
+use sqlx::{SqliteConnection, SqlitePool, prelude::*};
+
+/// A pending revert of a system's front to the base account, run by the [`crate::scheduler`].
+///
+/// A system has at most one revert for each source:
+/// - [`switch::Source::Timer`] for a timed switch (`/members switch --for`)
+/// - [`switch::Source::Inactivity`] for the system's inactivity timeout, which is pushed back whenever the system
+///   sends a message
+///
+/// Any switch cancels the system's pending reverts (see [`system::Id::switch_front`]).
+#[derive(FromRow, Debug)]
+#[allow(dead_code)]
+pub struct ScheduledRevert {
+    pub system_id: system::Id<Trusted>,
+    /// What the revert is recorded as in the front history
+    pub source: switch::Source,
+    pub revert_at: time::PrimitiveDateTime,
+}
+
+impl ScheduledRevert {
+    /// Fetches every revert that's due, oldest first
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_due(db: &SqlitePool) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            ScheduledRevert,
+            r#"
+            SELECT
+                system_id as "system_id: system::Id<Trusted>",
+                source,
+                revert_at as "revert_at: time::PrimitiveDateTime"
+            FROM scheduled_reverts
+            WHERE revert_at <= CURRENT_TIMESTAMP
+            ORDER BY revert_at
+            "#
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch due reverts")
+    }
+
+    /// Schedules a revert once a timed switch is over, replacing any other timed switch
+    #[tracing::instrument(skip(db))]
+    pub async fn schedule_timer(
+        system_id: system::Id<Trusted>,
+        duration: time::Duration,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        let source = switch::Source::Timer;
+        let seconds = duration.whole_seconds();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO scheduled_reverts (system_id, source, revert_at)
+            VALUES ($1, $2, datetime('now', '+' || $3 || ' seconds'))
+            ON CONFLICT (system_id, source) DO UPDATE SET revert_at = excluded.revert_at
+            "#,
+            system_id,
+            source,
+            seconds
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to schedule timed switch revert")
+        .map(|_| ())
+    }
+
+    /// (Re)schedules the inactivity revert from now, if the system has an inactivity timeout and anyone is fronting.
+    /// Otherwise, removes it
+    #[tracing::instrument(skip(db))]
+    pub async fn schedule_inactivity(
+        system_id: system::Id<Trusted>,
+        db: &mut SqliteConnection,
+    ) -> Result<(), sqlx::Error> {
+        let source = switch::Source::Inactivity;
+
+        sqlx::query!(
+            "DELETE FROM scheduled_reverts WHERE system_id = $1 AND source = $2",
+            system_id,
+            source
+        )
+        .execute(&mut *db)
+        .await
+        .attach_printable("Failed to clear inactivity revert")?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO scheduled_reverts (system_id, source, revert_at)
+            SELECT id, $2, datetime('now', '+' || inactivity_timeout || ' seconds')
+            FROM systems
+            WHERE id = $1 AND inactivity_timeout IS NOT NULL AND currently_fronting_member_id IS NOT NULL
+            "#,
+            system_id,
+            source
+        )
+        .execute(&mut *db)
+        .await
+        .attach_printable("Failed to schedule inactivity revert")
+        .map(|_| ())
+    }
+
+    /// Pushes the inactivity revert back to the full timeout from now, if there is one
+    #[tracing::instrument(skip(db))]
+    pub async fn postpone_inactivity(
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        let source = switch::Source::Inactivity;
+
+        sqlx::query!(
+            r#"
+            UPDATE scheduled_reverts
+            SET revert_at = datetime(
+                'now',
+                '+' || (SELECT inactivity_timeout FROM systems WHERE id = $1) || ' seconds'
+            )
+            WHERE system_id = $1 AND source = $2
+            "#,
+            system_id,
+            source
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to postpone inactivity revert")
+        .map(|_| ())
+    }
+
+    /// Cancels every pending revert for a system
+    #[tracing::instrument(skip(db))]
+    pub async fn cancel(
+        system_id: system::Id<Trusted>,
+        db: &mut SqliteConnection,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM scheduled_reverts WHERE system_id = $1",
+            system_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to cancel reverts")
+        .map(|_| ())
+    }
+}
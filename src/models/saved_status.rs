@@ -0,0 +1,82 @@
+use super::{system, trust::Trusted};
+use error_stack::{Result, ResultExt};
+use sqlx::{SqlitePool, prelude::*};
+
+/// The owner's own Slack status from before their status started mirroring the front.
+/// See [`crate::status`]
+#[derive(FromRow, Debug)]
+#[allow(dead_code)]
+pub struct SavedStatus {
+    pub system_id: system::Id<Trusted>,
+    /// Empty if the owner had no status
+    pub status_text: String,
+    /// Empty if the owner had no status emoji
+    pub status_emoji: String,
+    /// Only saved if the display name is mirrored too
+    pub display_name: Option<String>,
+}
+
+impl SavedStatus {
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch(
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            SavedStatus,
+            r#"
+            SELECT
+                system_id as "system_id: system::Id<Trusted>",
+                status_text,
+                status_emoji,
+                display_name
+            FROM saved_statuses
+            WHERE system_id = $1
+            "#,
+            system_id
+        )
+        .fetch_optional(db)
+        .await
+        .attach_printable("Failed to fetch saved status")
+    }
+
+    /// Saves the owner's own status. If one is already saved, only a missing display name is filled in,
+    /// so the status from before mirroring started is kept
+    #[tracing::instrument(skip(db))]
+    pub async fn save(
+        system_id: system::Id<Trusted>,
+        status_text: &str,
+        status_emoji: &str,
+        display_name: Option<&str>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO saved_statuses (system_id, status_text, status_emoji, display_name)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (system_id) DO UPDATE SET
+                display_name = coalesce(saved_statuses.display_name, excluded.display_name)
+            "#,
+            system_id,
+            status_text,
+            status_emoji,
+            display_name
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to save status")
+        .map(|_| ())
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn delete(
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!("DELETE FROM saved_statuses WHERE system_id = $1", system_id)
+            .execute(db)
+            .await
+            .attach_printable("Failed to delete saved status")
+            .map(|_| ())
+    }
+}
@@ -0,0 +1,317 @@
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, hash_map::Entry},
+};
+
+use crate::id;
+
+use super::{member, system, trust::Trusted};
+use error_stack::{Result, ResultExt};
+use sqlx::{SqliteConnection, SqlitePool, prelude::*};
+
+id!(
+    /// You cannot create a switch id, as it is internal generated-only.
+    ///
+    /// For an ID to be valid (trusted), it must
+    ///
+    /// - Be a valid ID in the database
+    /// - Be associated with a valid system (constrained at database level; no validation needed)
+    => Switch
+);
+
+#[derive(Debug, sqlx::Type, displaydoc::Display, PartialEq, Eq, Clone, Copy)]
+#[repr(i64)]
+/// What caused a switch
+pub enum Source {
+    /// Command
+    Command = 0,
+    /// Trigger
+    Trigger = 1,
+    /// Timer
+    Timer = 2,
+    /// Inactivity timeout
+    Inactivity = 3,
+}
+
+impl From<i64> for Source {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::Command,
+            1 => Self::Trigger,
+            2 => Self::Timer,
+            3 => Self::Inactivity,
+            _ => unreachable!(
+                "Invalid switch source value. This means the database and rust struct are out of sync"
+            ),
+        }
+    }
+}
+
+/// How much a member fronted over a period. See [`front_stats`]
+#[derive(Debug)]
+pub struct FrontStats {
+    pub member_id: member::Id<Trusted>,
+    /// Total time fronting, including time co-fronting
+    pub total: time::Duration,
+    /// The longest time fronting without a break. A switch that keeps the member in the front doesn't break it
+    pub longest: time::Duration,
+    /// How many times the member was switched in (as the primary fronter or a co-fronter)
+    pub switches: u32,
+}
+
+/// Works out how much each member fronted between `start` and `end`, from switches oldest first,
+/// like the ones from [`Switch::fetch_overlapping`]. Sorted by total fronting time, longest first.
+///
+/// Fronting before `start` isn't counted, but a stretch that began before it still counts towards the longest stretch
+/// from `start` on.
+pub fn front_stats(
+    switches: &[Switch],
+    co_fronters: &HashMap<i64, Vec<member::Id<Trusted>>>,
+    start: time::OffsetDateTime,
+    end: time::OffsetDateTime,
+) -> Vec<FrontStats> {
+    let mut stats = HashMap::<i64, FrontStats>::new();
+    // When each currently fronting member's stretch began
+    let mut stretches = HashMap::<i64, time::OffsetDateTime>::new();
+
+    for switch in switches {
+        let from = switch.created_at.assume_utc().max(start);
+        let to = switch
+            .ended_at
+            .map_or(end, time::PrimitiveDateTime::assume_utc)
+            .min(end);
+
+        let fronters = switch
+            .member_id
+            .into_iter()
+            .chain(
+                co_fronters
+                    .get(&switch.id.id)
+                    .into_iter()
+                    .flatten()
+                    .copied(),
+            )
+            .collect::<Vec<_>>();
+
+        stretches.retain(|member_id, stretch_start| {
+            if fronters.iter().any(|fronter| fronter.id == *member_id) {
+                return true;
+            }
+
+            if let Some(stats) = stats.get_mut(member_id) {
+                stats.longest = stats.longest.max(from - *stretch_start);
+            }
+
+            false
+        });
+
+        for fronter in fronters {
+            let stats = stats.entry(fronter.id).or_insert_with(|| FrontStats {
+                member_id: fronter,
+                total: time::Duration::ZERO,
+                longest: time::Duration::ZERO,
+                switches: 0,
+            });
+
+            stats.total += (to - from).max(time::Duration::ZERO);
+
+            if let Entry::Vacant(stretch) = stretches.entry(fronter.id) {
+                stretch.insert(from);
+
+                if switch.created_at.assume_utc() >= start {
+                    stats.switches += 1;
+                }
+            }
+        }
+    }
+
+    for (member_id, stretch_start) in stretches {
+        if let Some(stats) = stats.get_mut(&member_id) {
+            stats.longest = stats.longest.max(end - stretch_start);
+        }
+    }
+
+    let mut stats = stats.into_values().collect::<Vec<_>>();
+    stats.sort_by_key(|stats| Reverse(stats.total));
+    stats
+}
+
+/// A change of the fronting member, as recorded in the front history
+#[derive(FromRow, Debug)]
+#[allow(dead_code)]
+pub struct Switch {
+    pub id: Id<Trusted>,
+    pub system_id: system::Id<Trusted>,
+    /// The primary fronter switched to, or `None` for the base account
+    pub member_id: Option<member::Id<Trusted>>,
+    pub source: Source,
+    pub created_at: time::PrimitiveDateTime,
+    /// When the next switch happened, or `None` if this is still the current front
+    pub ended_at: Option<time::PrimitiveDateTime>,
+}
+
+impl Switch {
+    /// Records a switch. Meant to be run in the same transaction that changes the fronting member
+    #[tracing::instrument(skip(db))]
+    pub async fn insert(
+        system_id: system::Id<Trusted>,
+        member_id: Option<member::Id<Trusted>>,
+        co_fronters: &[member::Id<Trusted>],
+        source: Source,
+        db: &mut SqliteConnection,
+    ) -> Result<(), sqlx::Error> {
+        let switch_id = sqlx::query!(
+            r#"
+            INSERT INTO switches (system_id, member_id, source)
+            VALUES ($1, $2, $3)
+            RETURNING id as "id: Id<Trusted>"
+            "#,
+            system_id,
+            member_id,
+            source
+        )
+        .fetch_one(&mut *db)
+        .await
+        .attach_printable("Failed to insert switch")?
+        .id;
+
+        for co_fronter in co_fronters {
+            sqlx::query!(
+                "INSERT INTO switch_co_fronters (switch_id, member_id) VALUES ($1, $2)",
+                switch_id,
+                co_fronter
+            )
+            .execute(&mut *db)
+            .await
+            .attach_printable("Failed to insert switch co-fronter")?;
+        }
+
+        Ok(())
+    }
+
+    /// Fetches the co-fronters of every switch in the system, keyed by switch ID
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_co_fronters_by_system_id(
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<HashMap<i64, Vec<member::Id<Trusted>>>, sqlx::Error> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                switch_co_fronters.switch_id,
+                switch_co_fronters.member_id as "member_id: member::Id<Trusted>"
+            FROM
+                switch_co_fronters
+            JOIN
+                switches ON switches.id = switch_co_fronters.switch_id
+            WHERE
+                switches.system_id = $1
+            "#,
+            system_id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch switch co-fronters")?;
+
+        let mut co_fronters = HashMap::<_, Vec<_>>::new();
+
+        for record in records {
+            co_fronters
+                .entry(record.switch_id)
+                .or_default()
+                .push(record.member_id);
+        }
+
+        Ok(co_fronters)
+    }
+
+    /// Fetches a system's switches that were in effect at or after `since`, oldest first.
+    /// This includes the switch that was current at `since`, if there was one
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_overlapping(
+        system_id: system::Id<Trusted>,
+        since: time::PrimitiveDateTime,
+        db: &SqlitePool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Switch,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>",
+                member_id as "member_id: member::Id<Trusted>",
+                source,
+                created_at as "created_at: time::PrimitiveDateTime",
+                ended_at as "ended_at?: time::PrimitiveDateTime"
+            FROM (
+                SELECT
+                    *,
+                    LEAD(created_at) OVER (ORDER BY created_at, id) as ended_at
+                FROM
+                    switches
+                WHERE system_id = $1
+            ) AS history
+            WHERE ended_at IS NULL OR ended_at > datetime($2)
+            ORDER BY created_at, id
+            "#,
+            system_id,
+            since
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch switches")
+    }
+
+    /// Fetches a system's switches, newest first.
+    ///
+    /// Only switches made at or after `since` and including `member_id` (as the primary fronter or a co-fronter)
+    /// are returned, if they're given.
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_by_system_id(
+        system_id: system::Id<Trusted>,
+        since: Option<time::PrimitiveDateTime>,
+        member_id: Option<member::Id<Trusted>>,
+        limit: u32,
+        db: &SqlitePool,
+    ) -> Result<Vec<Self>, sqlx::Error> {
+        sqlx::query_as!(
+            Switch,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>",
+                member_id as "member_id: member::Id<Trusted>",
+                source,
+                created_at as "created_at: time::PrimitiveDateTime",
+                ended_at as "ended_at?: time::PrimitiveDateTime"
+            FROM (
+                SELECT
+                    *,
+                    LEAD(created_at) OVER (ORDER BY created_at, id) as ended_at
+                FROM
+                    switches
+                WHERE system_id = $1
+            ) AS history
+            WHERE
+                ($2 IS NULL OR created_at >= datetime($2)) AND
+                (
+                    $3 IS NULL OR
+                    member_id = $3 OR
+                    EXISTS (
+                        SELECT 1 FROM switch_co_fronters
+                        WHERE switch_co_fronters.switch_id = history.id AND switch_co_fronters.member_id = $3
+                    )
+                )
+            ORDER BY created_at DESC, id DESC
+            LIMIT $4
+            "#,
+            system_id,
+            since,
+            member_id,
+            limit
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Failed to fetch switches")
+    }
+}
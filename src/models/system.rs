@@ -1,17 +1,24 @@
 use crate::{
     fields, id,
     models::member::{DetectedMember, Member},
+    util,
 };
 
 use super::{
+    check_in::ScheduledCheckIn,
+    matcher::Matcher,
     member::{self},
+    privacy::Privacy,
+    revert::ScheduledRevert,
+    switch::{self, Switch},
     trigger::Trigger,
     trust::{Trustability, Trusted},
     user,
 };
 use error_stack::{Result, ResultExt};
 use redact::Secret;
-use sqlx::{SqlitePool, prelude::*};
+use slack_morphism::SlackChannelId;
+use sqlx::{SqliteConnection, SqlitePool, prelude::*};
 use tracing::debug;
 
 id!(
@@ -30,28 +37,58 @@ impl Id<Trusted> {
         Trigger::fetch_by_system_id(self, db).await
     }
 
+    /// Switches to a single fronting member, or to the base account if `None`.
+    /// This replaces the whole front, co-fronters included. Returns whether the front changed
     #[tracing::instrument(skip(db))]
     pub async fn change_fronting_member(
         self,
         new_active_member_id: Option<member::Id<Trusted>>,
+        source: switch::Source,
         db: &SqlitePool,
-    ) -> Result<Option<Member>, sqlx::Error> {
+    ) -> Result<bool, sqlx::Error> {
         debug!(
             "Changing active member for {} to {:?}",
             self, new_active_member_id
         );
 
-        let mut new_active_member = None;
+        fields!(new_active_member_id = ?&new_active_member_id);
 
-        if let Some(new_active_member_id) = new_active_member_id {
-            new_active_member = Some(
-                Member::fetch_by_id(new_active_member_id, db)
-                    .await
-                    .attach_printable("Failed to fetch member")?,
-            );
-        }
+        self.switch_front(new_active_member_id.as_slice(), source, db)
+            .await
+    }
+
+    /// Sets everyone fronting. The first member is the primary fronter, and the rest are co-fronters.
+    /// An empty front switches to the base account.
+    ///
+    /// The switch is only recorded if it changed anything. Returns whether it did.
+    /// A switch cancels any pending [`ScheduledRevert`]s, and restarts the inactivity timer and [`ScheduledCheckIn`]
+    #[tracing::instrument(skip(db))]
+    pub async fn switch_front(
+        self,
+        fronters: &[member::Id<Trusted>],
+        source: switch::Source,
+        db: &SqlitePool,
+    ) -> Result<bool, sqlx::Error> {
+        let (primary, co_fronters) = match fronters.split_first() {
+            Some((primary, co_fronters)) => (Some(*primary), co_fronters),
+            None => (None, [].as_slice()),
+        };
 
-        fields!(new_active_member = ?&new_active_member);
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+
+        let current = Self::fetch_fronting_member_ids(self, &mut transaction).await?;
+
+        let unchanged = current.first() == fronters.first()
+            && current.len() == fronters.len()
+            && co_fronters.iter().all(|id| current.contains(id));
+
+        if unchanged {
+            debug!("Front is unchanged, not recording a switch");
+            return Ok(false);
+        }
 
         sqlx::query!(
             r#"
@@ -59,22 +96,74 @@ impl Id<Trusted> {
             SET currently_fronting_member_id = $1
             WHERE id = $2
             "#,
-            new_active_member_id,
+            primary,
             self.id
         )
-        .execute(db)
+        .execute(&mut *transaction)
         .await
         .attach_printable("Failed to update system active member")?;
 
-        Ok(new_active_member)
+        sqlx::query!("DELETE FROM co_fronters WHERE system_id = $1", self.id)
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to clear co-fronters")?;
+
+        for co_fronter in co_fronters {
+            sqlx::query!(
+                "INSERT INTO co_fronters (system_id, member_id) VALUES ($1, $2)",
+                self.id,
+                co_fronter
+            )
+            .execute(&mut *transaction)
+            .await
+            .attach_printable("Failed to insert co-fronter")?;
+        }
+
+        Switch::insert(self, primary, co_fronters, source, &mut transaction).await?;
+
+        ScheduledRevert::cancel(self, &mut transaction).await?;
+        ScheduledRevert::schedule_inactivity(self, &mut transaction).await?;
+        ScheduledCheckIn::schedule(self, &mut transaction).await?;
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit switch")?;
+
+        Ok(true)
     }
 
+    /// Everyone fronting, with the primary fronter first. Empty if the base account is fronting
     #[tracing::instrument(skip(db))]
-    pub async fn currently_fronting_member_id(
-        &self,
+    pub async fn fronting_member_ids(
+        self,
         db: &SqlitePool,
-    ) -> Result<Option<member::Id<Trusted>>, sqlx::Error> {
-        sqlx::query!(
+    ) -> Result<Vec<member::Id<Trusted>>, sqlx::Error> {
+        let mut connection = db
+            .acquire()
+            .await
+            .attach_printable("Failed to acquire connection")?;
+
+        Self::fetch_fronting_member_ids(self, &mut connection).await
+    }
+
+    /// Everyone fronting, with the primary fronter first. Empty if the base account is fronting
+    #[tracing::instrument(skip(db))]
+    pub async fn fronting_members(self, db: &SqlitePool) -> Result<Vec<Member>, sqlx::Error> {
+        let mut members = Vec::new();
+
+        for member_id in self.fronting_member_ids(db).await? {
+            members.push(Member::fetch_by_id(member_id, db).await?);
+        }
+
+        Ok(members)
+    }
+
+    async fn fetch_fronting_member_ids(
+        self,
+        db: &mut SqliteConnection,
+    ) -> Result<Vec<member::Id<Trusted>>, sqlx::Error> {
+        let primary = sqlx::query!(
             r#"
             SELECT currently_fronting_member_id as "id: member::Id<Trusted>"
             FROM systems
@@ -82,10 +171,294 @@ impl Id<Trusted> {
             "#,
             self.id
         )
-        .fetch_one(db)
+        .fetch_one(&mut *db)
+        .await
+        .attach_printable("Failed to fetch system currently fronting member id")?
+        .id;
+
+        let Some(primary) = primary else {
+            return Ok(Vec::new());
+        };
+
+        let co_fronters = sqlx::query!(
+            r#"
+            SELECT member_id as "member_id: member::Id<Trusted>"
+            FROM co_fronters
+            WHERE system_id = $1
+            ORDER BY rowid
+            "#,
+            self.id
+        )
+        .fetch_all(&mut *db)
+        .await
+        .attach_printable("Failed to fetch co-fronters")?;
+
+        Ok(std::iter::once(primary)
+            .chain(co_fronters.into_iter().map(|record| record.member_id))
+            .collect())
+    }
+
+    /// Updates how messages without a trigger are proxied. Any setting left as `None` is unchanged
+    #[tracing::instrument(skip(db))]
+    pub async fn update_autoproxy(
+        self,
+        mode: Option<AutoproxyMode>,
+        member_id: Option<member::Id<Trusted>>,
+        auto_switch_on_trigger: Option<bool>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET
+                autoproxy_mode = coalesce($2, autoproxy_mode),
+                autoproxy_member_id = coalesce($3, autoproxy_member_id),
+                auto_switch_on_trigger = coalesce($4, auto_switch_on_trigger)
+            WHERE id = $1
+            "#,
+            self.id,
+            mode,
+            member_id,
+            auto_switch_on_trigger
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system autoproxy")
+        .map(|_| ())
+    }
+
+    /// Sets the member who last used a trigger, for [`AutoproxyMode::Latch`]
+    #[tracing::instrument(skip(db))]
+    pub async fn set_latch_member(
+        self,
+        member_id: member::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE systems SET latch_member_id = $2 WHERE id = $1",
+            self.id,
+            member_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system latch member")
+        .map(|_| ())
+    }
+
+    /// Sets how long a thread stays latched without being used, or turns thread latching off with `None`
+    #[tracing::instrument(skip(db))]
+    pub async fn set_thread_latch_timeout(
+        self,
+        timeout: Option<time::Duration>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        let timeout = timeout.map(time::Duration::whole_seconds);
+
+        sqlx::query!(
+            "UPDATE systems SET thread_latch_timeout = $2 WHERE id = $1",
+            self.id,
+            timeout
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system thread latch timeout")
+        .map(|_| ())
+    }
+
+    /// Sets how long the system can go without sending a message before the front reverts to the base account,
+    /// or turns inactivity reverts off with `None`. The inactivity timer restarts from now
+    #[tracing::instrument(skip(db))]
+    pub async fn set_inactivity_timeout(
+        self,
+        timeout: Option<time::Duration>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        let timeout = timeout.map(time::Duration::whole_seconds);
+
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+
+        sqlx::query!(
+            "UPDATE systems SET inactivity_timeout = $2 WHERE id = $1",
+            self.id,
+            timeout
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to update system inactivity timeout")?;
+
+        ScheduledRevert::schedule_inactivity(self, &mut transaction).await?;
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit inactivity timeout")
+    }
+
+    /// Sets how long the front can stay the same before the owner is sent a check-in reminder,
+    /// or turns reminders off with `None`. The next reminder is scheduled from now
+    #[tracing::instrument(skip(db))]
+    pub async fn set_check_in_interval(
+        self,
+        interval: Option<time::Duration>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        let interval = interval.map(time::Duration::whole_seconds);
+
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+
+        sqlx::query!(
+            "UPDATE systems SET check_in_interval = $2 WHERE id = $1",
+            self.id,
+            interval
+        )
+        .execute(&mut *transaction)
+        .await
+        .attach_printable("Failed to update system check-in interval")?;
+
+        ScheduledCheckIn::schedule(self, &mut transaction).await?;
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit check-in interval")
+    }
+
+    /// Sets the channel switches are announced in, or turns announcements off with `None`
+    #[tracing::instrument(skip(db))]
+    pub async fn set_announcement_channel(
+        self,
+        channel_id: Option<&SlackChannelId>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        let channel_id = channel_id.map(|channel_id| channel_id.0.as_str());
+
+        sqlx::query!(
+            "UPDATE systems SET announcement_channel_id = $2 WHERE id = $1",
+            self.id,
+            channel_id
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system announcement channel")
+        .map(|_| ())
+    }
+
+    /// Sets whether switches from triggers are announced too
+    #[tracing::instrument(skip(db))]
+    pub async fn set_announce_trigger_switches(
+        self,
+        announce: bool,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE systems SET announce_trigger_switches = $2 WHERE id = $1",
+            self.id,
+            announce
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system announcement settings")
+        .map(|_| ())
+    }
+
+    /// Sets the system tag, or removes it with `None`
+    #[tracing::instrument(skip(db))]
+    pub async fn set_tag(self, tag: Option<&str>, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        sqlx::query!("UPDATE systems SET tag = $2 WHERE id = $1", self.id, tag)
+            .execute(db)
+            .await
+            .attach_printable("Failed to update system tag")
+            .map(|_| ())
+    }
+
+    /// Updates who can see the front and member list. Any setting left as `None` is unchanged
+    #[tracing::instrument(skip(db))]
+    pub async fn update_privacy(
+        self,
+        front: Option<Privacy>,
+        member_list: Option<Privacy>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET
+                front_privacy = coalesce($2, front_privacy),
+                member_list_privacy = coalesce($3, member_list_privacy)
+            WHERE id = $1
+            "#,
+            self.id,
+            front,
+            member_list
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system privacy")
+        .map(|_| ())
+    }
+
+    /// Updates how the owner's Slack status mirrors the front. Any setting left as `None` is unchanged
+    #[tracing::instrument(skip(db))]
+    pub async fn update_status_mirroring(
+        self,
+        mirror_status: Option<bool>,
+        mirror_display_name: Option<bool>,
+        status_emoji: Option<String>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET
+                mirror_status = coalesce($2, mirror_status),
+                mirror_display_name = coalesce($3, mirror_display_name),
+                status_emoji = coalesce($4, status_emoji)
+            WHERE id = $1
+            "#,
+            self.id,
+            mirror_status,
+            mirror_display_name,
+            status_emoji
+        )
+        .execute(db)
+        .await
+        .attach_printable("Failed to update system status mirroring")
+        .map(|_| ())
+    }
+
+    /// Updates the system's settings. Any setting left as `None` is unchanged
+    #[tracing::instrument(skip(db))]
+    pub async fn update_settings(
+        self,
+        split_messages: Option<bool>,
+        proxy_escape: Option<String>,
+        strip_proxy_escape: Option<bool>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE systems
+            SET
+                split_messages = coalesce($2, split_messages),
+                proxy_escape = coalesce($3, proxy_escape),
+                strip_proxy_escape = coalesce($4, strip_proxy_escape)
+            WHERE id = $1
+            "#,
+            self.id,
+            split_messages,
+            proxy_escape,
+            strip_proxy_escape
+        )
+        .execute(db)
         .await
-        .attach_printable("Failed to fetch system currently fronting member id")
-        .map(|row| row.id)
+        .attach_printable("Failed to update system settings")
+        .map(|_| ())
     }
 
     #[tracing::instrument(skip(db))]
@@ -98,6 +471,23 @@ impl Id<Trusted> {
                 owner_id as "owner_id: user::Id<Trusted>",
                 currently_fronting_member_id as "currently_fronting_member_id: member::Id<Trusted>",
                 auto_switch_on_trigger,
+                autoproxy_mode,
+                autoproxy_member_id as "autoproxy_member_id: member::Id<Trusted>",
+                latch_member_id as "latch_member_id: member::Id<Trusted>",
+                thread_latch_timeout,
+                inactivity_timeout,
+                announcement_channel_id,
+                announce_trigger_switches,
+                mirror_status,
+                mirror_display_name,
+                status_emoji,
+                check_in_interval,
+                tag,
+                front_privacy,
+                member_list_privacy,
+                split_messages,
+                proxy_escape,
+                strip_proxy_escape,
                 slack_oauth_token,
                 created_at as "created_at: time::PrimitiveDateTime"
             FROM systems
@@ -111,6 +501,24 @@ impl Id<Trusted> {
     }
 }
 
+/// The longest username Slack shows on a message
+pub const MAX_USERNAME_LENGTH: usize = 80;
+
+/// The longest a [`System::tag`] can be, leaving room in the username for the member's name
+pub const MAX_TAG_LENGTH: usize = 40;
+
+/// Shortens text to at most `max` characters, ending it with an ellipsis if anything was cut off
+fn truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    text.chars()
+        .take(max.saturating_sub(1))
+        .chain(std::iter::once('…'))
+        .collect()
+}
+
 #[derive(Debug, FromRow, PartialEq, Eq, Clone)]
 #[sqlx(transparent)]
 pub struct SlackOauthToken(Secret<String>);
@@ -128,7 +536,7 @@ impl From<String> for SlackOauthToken {
 }
 
 #[derive(FromRow, Debug)]
-#[allow(dead_code)]
+#[allow(dead_code, clippy::struct_excessive_bools)]
 /// A plural system
 ///
 /// A system has 1 owner and many members.
@@ -146,12 +554,152 @@ pub struct System {
     pub currently_fronting_member_id: Option<member::Id<Trusted>>,
     /// Whether a [`trigger::Trigger`] activation changes the active member to the member the trigger is associated with
     pub auto_switch_on_trigger: bool,
+    /// How messages without a trigger are proxied
+    pub autoproxy_mode: AutoproxyMode,
+    /// The member messages are sent as in [`AutoproxyMode::Member`]
+    pub autoproxy_member_id: Option<member::Id<Trusted>>,
+    /// The member who last used a trigger, for [`AutoproxyMode::Latch`]
+    pub latch_member_id: Option<member::Id<Trusted>>,
+    /// How long, in seconds, a thread stays latched to the member who last used a trigger in it without being used.
+    /// `None` turns thread latching off
+    pub thread_latch_timeout: Option<i64>,
+    /// How long, in seconds, the system can go without sending a message before the front reverts to the base account.
+    /// `None` turns inactivity reverts off
+    pub inactivity_timeout: Option<i64>,
+    /// The channel switches are announced in, if any. See [`Self::announcement_channel`]
+    pub announcement_channel_id: Option<String>,
+    /// Whether switches from triggers are announced too
+    pub announce_trigger_switches: bool,
+    /// Whether the owner's Slack status shows who's fronting. See [`crate::status`]
+    pub mirror_status: bool,
+    /// Whether the owner's Slack display name is changed to the primary fronter's too
+    pub mirror_display_name: bool,
+    /// The status emoji used while mirroring (e.g. `:sparkles:`)
+    pub status_emoji: String,
+    /// How long, in seconds, the front can stay the same before the owner is sent a check-in reminder.
+    /// `None` turns reminders off
+    pub check_in_interval: Option<i64>,
+    /// Shown after every member's name on proxied messages (e.g. `| Sunflower System`), unless the member opts out.
+    /// See [`Self::proxy_username`]
+    pub tag: Option<String>,
+    /// Whether other users can see who's fronting
+    pub front_privacy: Privacy,
+    /// Whether other users can list the system's members
+    pub member_list_privacy: Privacy,
+    /// Whether a message with triggers on several lines is split into one message per member
+    pub split_messages: bool,
+    /// A message starting with this is never proxied (e.g. `\`). This is stored unescaped
+    pub proxy_escape: String,
+    /// Whether the [`Self::proxy_escape`] is removed from the original message
+    pub strip_proxy_escape: bool,
     /// The Slack OAuth token for the system
     pub slack_oauth_token: SlackOauthToken,
     pub created_at: time::PrimitiveDateTime,
 }
 
+#[derive(Debug, sqlx::Type, displaydoc::Display, PartialEq, Eq, clap::ValueEnum, Clone, Copy)]
+#[repr(i64)]
+/// How messages without a trigger are proxied
+#[ignore_extra_doc_attributes]
+pub enum AutoproxyMode {
+    /// Off
+    ///
+    /// Messages without a trigger are sent from your own account
+    Off = 0,
+    /// Front
+    ///
+    /// Messages are sent as the primary fronter
+    Front = 1,
+    /// Latch
+    ///
+    /// Messages are sent as whoever last used a trigger, without switching the front
+    Latch = 2,
+    /// Member
+    ///
+    /// Messages are always sent as one member
+    Member = 3,
+}
+
+impl From<i64> for AutoproxyMode {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::Off,
+            1 => Self::Front,
+            2 => Self::Latch,
+            3 => Self::Member,
+            _ => unreachable!(
+                "Invalid autoproxy mode value. This means the database and rust struct are out of sync"
+            ),
+        }
+    }
+}
+
 impl System {
+    /// The member a message without a trigger is sent as, if any
+    pub const fn autoproxy_member_id(&self) -> Option<member::Id<Trusted>> {
+        match self.autoproxy_mode {
+            AutoproxyMode::Off => None,
+            AutoproxyMode::Front => self.currently_fronting_member_id,
+            AutoproxyMode::Latch => self.latch_member_id,
+            AutoproxyMode::Member => self.autoproxy_member_id,
+        }
+    }
+
+    /// How long a thread stays latched without being used, if thread latching is on.
+    /// See [`super::autoproxy::ThreadLatch`]
+    pub fn thread_latch_timeout(&self) -> Option<time::Duration> {
+        self.thread_latch_timeout.map(time::Duration::seconds)
+    }
+
+    /// How long the system can go without sending a message before the front reverts to the base account, if at all.
+    /// See [`ScheduledRevert`]
+    pub fn inactivity_timeout(&self) -> Option<time::Duration> {
+        self.inactivity_timeout.map(time::Duration::seconds)
+    }
+
+    /// How long the front can stay the same before the owner is sent a check-in reminder, if at all.
+    /// See [`ScheduledCheckIn`]
+    pub fn check_in_interval(&self) -> Option<time::Duration> {
+        self.check_in_interval.map(time::Duration::seconds)
+    }
+
+    /// The name a member's messages are sent under: their display name, followed by the system tag if they show it.
+    ///
+    /// Slack cuts usernames off at [`MAX_USERNAME_LENGTH`] characters, so the display name is shortened to keep
+    /// the tag in view
+    pub fn proxy_username(&self, display_name: &str, show_tag: bool) -> String {
+        let Some(tag) = self.tag.as_deref().filter(|_| show_tag) else {
+            return truncate(display_name, MAX_USERNAME_LENGTH);
+        };
+
+        // The tag is at most MAX_TAG_LENGTH characters, so there's always room for some of the name
+        let name_length = MAX_USERNAME_LENGTH.saturating_sub(tag.chars().count() + 1);
+        format!("{} {tag}", truncate(display_name, name_length))
+    }
+
+    /// The channel switches are announced in, if any
+    pub fn announcement_channel(&self) -> Option<SlackChannelId> {
+        self.announcement_channel_id
+            .clone()
+            .map(SlackChannelId::new)
+    }
+
+    /// Records that a member used a trigger, for [`AutoproxyMode::Latch`]
+    #[tracing::instrument(skip(self, db), fields(system_id = %self.id))]
+    pub async fn latch(
+        &mut self,
+        member_id: member::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        if self.autoproxy_mode != AutoproxyMode::Latch || self.latch_member_id == Some(member_id) {
+            return Ok(());
+        }
+
+        self.id.set_latch_member(member_id, db).await?;
+        self.latch_member_id = Some(member_id);
+        Ok(())
+    }
+
     #[tracing::instrument(skip(db))]
     pub async fn fetch_by_user_id<T>(
         user_id: &user::Id<T>,
@@ -168,6 +716,23 @@ impl System {
                 owner_id as "owner_id: user::Id<Trusted>",
                 currently_fronting_member_id as "currently_fronting_member_id: member::Id<Trusted>",
                 auto_switch_on_trigger,
+                autoproxy_mode,
+                autoproxy_member_id as "autoproxy_member_id: member::Id<Trusted>",
+                latch_member_id as "latch_member_id: member::Id<Trusted>",
+                thread_latch_timeout,
+                inactivity_timeout,
+                announcement_channel_id,
+                announce_trigger_switches,
+                mirror_status,
+                mirror_display_name,
+                status_emoji,
+                check_in_interval,
+                tag,
+                front_privacy,
+                member_list_privacy,
+                split_messages,
+                proxy_escape,
+                strip_proxy_escape,
                 slack_oauth_token,
                 created_at as "created_at: time::PrimitiveDateTime"
             FROM
@@ -182,27 +747,20 @@ impl System {
         .attach_printable("Error fetching system")
     }
 
-    #[tracing::instrument(skip(db))]
-    pub async fn active_member(&self, db: &SqlitePool) -> Result<Option<Member>, sqlx::Error> {
-        match self.currently_fronting_member_id {
-            Some(id) => Ok(Some(Member::fetch_by_id(id, db).await?)),
-            None => Ok(None),
-        }
-    }
-
     #[tracing::instrument(skip(db))]
     pub async fn change_fronting_member(
         &mut self,
         new_fronting_member_id: Option<member::Id<Trusted>>,
+        source: switch::Source,
         db: &SqlitePool,
-    ) -> Result<Option<Member>, sqlx::Error> {
-        let new_active_member = self
+    ) -> Result<bool, sqlx::Error> {
+        let changed = self
             .id
-            .change_fronting_member(new_fronting_member_id, db)
+            .change_fronting_member(new_fronting_member_id, source, db)
             .await?;
 
         self.currently_fronting_member_id = new_fronting_member_id;
-        Ok(new_active_member)
+        Ok(changed)
     }
 
     pub async fn members(&self, db: &SqlitePool) -> Result<Vec<Member>, sqlx::Error> {
@@ -219,7 +777,17 @@ impl System {
                 pronouns,
                 name_pronunciation,
                 name_recording_url,
+                description,
+                color,
+                birthday,
                 enabled,
+                show_system_tag,
+                privacy,
+                name_privacy,
+                pronoun_privacy,
+                description_privacy,
+                birthday_privacy,
+                custom_fields_privacy,
                 created_at as "created_at: time::PrimitiveDateTime"
             FROM
                 members
@@ -236,31 +804,37 @@ impl System {
         &self,
         db: &SqlitePool,
         message: &str,
+        channel_id: &SlackChannelId,
     ) -> Result<Option<DetectedMember>, sqlx::Error> {
         debug!(message, "Finding detected member if there is a match");
-        sqlx::query_as!(
-            DetectedMember,
+
+        let matcher = Matcher::fetch(self.id, db).await?;
+
+        let Some(trigger) = matcher.find(&util::unescape(message), channel_id) else {
+            return Ok(None);
+        };
+
+        let member = sqlx::query!(
             r#"
-                SELECT
-                    members.id as "id: member::Id<Trusted>",
-                    display_name,
-                    profile_picture_url,
-                    triggers.text as trigger_text,
-                    triggers.typ
-                FROM
-                    members
-                JOIN
-                    triggers ON members.id = triggers.member_id
-                WHERE
-                    -- See trigger.rs file for all types and names
-                    members.enabled = TRUE AND
-                    ((triggers.typ = 0 AND $1 LIKE '%' || triggers.text) OR
-                    (triggers.typ = 1 AND $1 LIKE triggers.text || '%'))
+            SELECT
+                display_name,
+                profile_picture_url,
+                show_system_tag
+            FROM members
+            WHERE id = $1
             "#,
-            message
+            trigger.member_id
         )
-        .fetch_optional(db)
+        .fetch_one(db)
         .await
-        .attach_printable("Failed to fetch triggered member")
+        .attach_printable("Failed to fetch triggered member")?;
+
+        Ok(Some(DetectedMember {
+            id: trigger.member_id,
+            display_name: member.display_name,
+            profile_picture_url: member.profile_picture_url,
+            show_system_tag: member.show_system_tag,
+            content: Some(trigger.content),
+        }))
     }
 }
@@ -1,13 +1,15 @@
-use std::str::FromStr;
+use std::{collections::HashMap, ops::Range, str::FromStr};
 
-use crate::id;
+use crate::{id, util};
 
 use super::{
-    member, system,
+    matcher, member, system,
     trust::{Trusted, Untrusted},
 };
 use error_stack::{Result, ResultExt};
-use sqlx::{SqlitePool, prelude::*, sqlite::SqliteQueryResult};
+use regex::{Regex, RegexBuilder};
+use slack_morphism::SlackChannelId;
+use sqlx::{SqliteConnection, SqlitePool, prelude::*};
 
 id!(
     /// For an ID to be trusted, it must
@@ -41,17 +43,48 @@ impl Id<Untrusted> {
 
 impl Id<Trusted> {
     #[tracing::instrument(skip(db))]
-    pub async fn delete(self, db: &SqlitePool) -> Result<SqliteQueryResult, sqlx::Error> {
-        sqlx::query!(
+    pub async fn fetch(self, db: &SqlitePool) -> Result<Trigger, sqlx::Error> {
+        sqlx::query_as!(
+            Trigger,
+            r#"
+            SELECT
+                id as "id: Id<Trusted>",
+                member_id as "member_id: member::Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>",
+                text,
+                typ,
+                case_sensitive as "case_sensitive: bool",
+                ignore_whitespace as "ignore_whitespace: bool",
+                channel_scope
+            FROM
+                triggers
+            WHERE id = $1
+            "#,
+            self.id
+        )
+        .fetch_one(db)
+        .await
+        .attach_printable("Error fetching trigger")
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn delete(self, db: &SqlitePool) -> Result<(), sqlx::Error> {
+        let system_id = sqlx::query!(
             r#"
                 DELETE FROM triggers
                 WHERE id = $1
+                RETURNING system_id as "system_id: system::Id<Trusted>"
             "#,
             self.id
         )
-        .execute(db)
+        .fetch_one(db)
         .await
-        .attach_printable("Error deleting trigger")
+        .attach_printable("Error deleting trigger")?
+        .system_id;
+
+        matcher::invalidate(system_id);
+
+        Ok(())
     }
 
     #[tracing::instrument(skip(db))]
@@ -59,26 +92,122 @@ impl Id<Trusted> {
         self,
         typ: Option<Type>,
         content: Option<String>,
+        case_sensitive: Option<bool>,
+        ignore_whitespace: Option<bool>,
         db: &SqlitePool,
     ) -> error_stack::Result<Self, sqlx::Error> {
-        sqlx::query!(
+        let record = sqlx::query!(
             r#"
             UPDATE triggers
             SET
                 typ = coalesce($2, typ),
-                text = coalesce($3, text)
+                text = coalesce($3, text),
+                case_sensitive = coalesce($4, case_sensitive),
+                ignore_whitespace = coalesce($5, ignore_whitespace)
             WHERE id = $1
             RETURNING
-                id as "id: Id<Trusted>"
+                id as "id: Id<Trusted>",
+                system_id as "system_id: system::Id<Trusted>"
             "#,
             self,
             typ,
-            content
+            content,
+            case_sensitive,
+            ignore_whitespace
         )
         .fetch_one(db)
         .await
-        .attach_printable("Failed to update trigger")
-        .map(|record| record.id)
+        .attach_printable("Failed to update trigger")?;
+
+        matcher::invalidate(record.system_id);
+
+        Ok(record.id)
+    }
+
+    #[tracing::instrument(skip(db))]
+    pub async fn channels(self, db: &SqlitePool) -> Result<Vec<SlackChannelId>, sqlx::Error> {
+        sqlx::query!(
+            "SELECT channel_id FROM trigger_channels WHERE trigger_id = $1",
+            self.id
+        )
+        .fetch_all(db)
+        .await
+        .map(|records| {
+            records
+                .into_iter()
+                .map(|record| SlackChannelId::new(record.channel_id))
+                .collect()
+        })
+        .attach_printable("Error fetching trigger channels")
+    }
+
+    /// Replaces the channels the trigger can be used in
+    #[tracing::instrument(skip(db))]
+    pub async fn set_channel_filter(
+        self,
+        filter: &ChannelFilter,
+        db: &SqlitePool,
+    ) -> Result<(), sqlx::Error> {
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+
+        let system_id = self.write_channel_filter(filter, &mut transaction).await?;
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit trigger channels")?;
+
+        matcher::invalidate(system_id);
+
+        Ok(())
+    }
+
+    /// Writes the channel filter without invalidating the matcher, so it can be part of a bigger transaction.
+    ///
+    /// Returns the system the trigger belongs to
+    async fn write_channel_filter(
+        self,
+        filter: &ChannelFilter,
+        db: &mut SqliteConnection,
+    ) -> Result<system::Id<Trusted>, sqlx::Error> {
+        let system_id = sqlx::query!(
+            r#"
+            UPDATE triggers
+            SET channel_scope = $2
+            WHERE id = $1
+            RETURNING system_id as "system_id: system::Id<Trusted>"
+            "#,
+            self.id,
+            filter.scope
+        )
+        .fetch_one(&mut *db)
+        .await
+        .attach_printable("Failed to update trigger channel scope")?
+        .system_id;
+
+        sqlx::query!(
+            "DELETE FROM trigger_channels WHERE trigger_id = $1",
+            self.id
+        )
+        .execute(&mut *db)
+        .await
+        .attach_printable("Failed to clear trigger channels")?;
+
+        for channel in &filter.channels {
+            sqlx::query!(
+                "INSERT INTO trigger_channels (trigger_id, channel_id) VALUES ($1, $2)",
+                self.id,
+                channel.0
+            )
+            .execute(&mut *db)
+            .await
+            .attach_printable("Failed to insert trigger channel")?;
+        }
+
+        Ok(system_id)
     }
 }
 
@@ -97,6 +226,285 @@ pub enum Type {
     ///
     /// Matches the beginning of a message (e.g. "]J" would match "]J hello")
     Prefix = 1,
+    /// Regex
+    ///
+    /// Matches a regular expression with a `text` capture group for the message content
+    /// (e.g. "^J:(?<text>.*)$" would match "J: hello")
+    Regex = 2,
+    /// Paired
+    ///
+    /// Matches both the beginning and end of a message, with `text` marking where the message content goes
+    /// (e.g. "[text]" would match "[hello]", but not "[hello" or "hello]")
+    Paired = 3,
+}
+
+/// The name of the capture group a [`Type::Regex`] trigger uses for the message content
+pub const REGEX_CONTENT_GROUP: &str = "text";
+
+/// The placeholder for the message content in a [`Type::Paired`] trigger
+pub const PAIRED_PLACEHOLDER: &str = "text";
+
+impl Type {
+    /// Finds the range of the message content (i.e. the message without the trigger) if the trigger matches.
+    ///
+    /// Both `text` and `message` should be unescaped (see [`crate::util::unescape`]).
+    pub fn content_range(
+        self,
+        text: &str,
+        message: &str,
+        options: MatchOptions,
+    ) -> Option<Range<usize>> {
+        let range = match self.ends(text) {
+            Some((start_tag, end_tag)) => {
+                let (start_tag, end_tag) = if options.ignore_whitespace {
+                    (start_tag.trim(), end_tag.trim())
+                } else {
+                    (start_tag, end_tag)
+                };
+
+                let mut range = options.trim(message, 0..message.len());
+                range.start += match_start(&message[range.clone()], start_tag, options)?;
+                range.end = range.start + match_end(&message[range.clone()], end_tag, options)?;
+                range
+            }
+            None => Self::regex(text, options)
+                .ok()?
+                .captures(message)?
+                .name(REGEX_CONTENT_GROUP)?
+                .range(),
+        };
+
+        Some(options.trim(message, range))
+    }
+
+    /// Compiles the regex for a [`Self::Regex`] trigger
+    pub fn regex(text: &str, options: MatchOptions) -> std::result::Result<Regex, regex::Error> {
+        RegexBuilder::new(text)
+            .case_insensitive(!options.case_sensitive)
+            .build()
+    }
+
+    /// Checks that the trigger text is usable for this type of trigger.
+    ///
+    /// `text` should be unescaped (see [`crate::util::unescape`]).
+    pub fn validate(self, text: &str) -> std::result::Result<(), InvalidTrigger> {
+        if text.is_empty() {
+            return Err(InvalidTrigger::Empty);
+        }
+
+        match self {
+            Self::Prefix | Self::Suffix => {}
+            Self::Regex => {
+                let regex = Regex::new(text)?;
+
+                if !regex
+                    .capture_names()
+                    .any(|name| name == Some(REGEX_CONTENT_GROUP))
+                {
+                    return Err(InvalidTrigger::MissingContentGroup);
+                }
+            }
+            Self::Paired => {
+                let (prefix, suffix) = text
+                    .split_once(PAIRED_PLACEHOLDER)
+                    .ok_or(InvalidTrigger::MissingPlaceholder)?;
+
+                if prefix.is_empty() || suffix.is_empty() {
+                    return Err(InvalidTrigger::UnpairedTag);
+                }
+
+                // There's no way to escape `text`, so it has to be clear which one is the placeholder
+                if prefix.contains(PAIRED_PLACEHOLDER) || suffix.contains(PAIRED_PLACEHOLDER) {
+                    return Err(InvalidTrigger::AmbiguousPlaceholder);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks whether two triggers overlap, meaning a message meant for one can also match the other
+    /// (e.g. the prefixes `J` and `J:`).
+    ///
+    /// Only the ends both triggers have are compared, so a prefix never overlaps a suffix.
+    /// Regex triggers can't be compared, so they never overlap.
+    /// Both texts should be unescaped (see [`crate::util::unescape`]).
+    pub fn overlaps(self, text: &str, other_typ: Self, other_text: &str) -> bool {
+        let (Some((start, end)), Some((other_start, other_end))) =
+            (self.ends(text), other_typ.ends(other_text))
+        else {
+            return false;
+        };
+
+        let shares_start = !start.is_empty() && !other_start.is_empty();
+        let shares_end = !end.is_empty() && !other_end.is_empty();
+
+        (shares_start || shares_end)
+            && (!shares_start || start.starts_with(other_start) || other_start.starts_with(start))
+            && (!shares_end || end.ends_with(other_end) || other_end.ends_with(end))
+    }
+
+    /// The fixed text a trigger needs at the start and end of a message. Either can be empty.
+    ///
+    /// Returns `None` for regex triggers, as they have no fixed text.
+    fn ends(self, text: &str) -> Option<(&str, &str)> {
+        match self {
+            Self::Prefix => Some((text, "")),
+            Self::Suffix => Some(("", text)),
+            Self::Paired => text.split_once(PAIRED_PLACEHOLDER),
+            Self::Regex => None,
+        }
+    }
+}
+
+#[derive(
+    Debug, sqlx::Type, displaydoc::Display, PartialEq, Eq, clap::ValueEnum, Clone, Copy, Default,
+)]
+#[repr(i64)]
+/// Which channels a trigger can be used in
+#[ignore_extra_doc_attributes]
+pub enum ChannelScope {
+    /// Everywhere
+    ///
+    /// Used in every channel
+    #[default]
+    Everywhere = 0,
+    /// Only in
+    ///
+    /// Only used in the listed channels
+    Only = 1,
+    /// Everywhere except
+    ///
+    /// Used in every channel except the listed ones
+    Except = 2,
+}
+
+impl From<i64> for ChannelScope {
+    fn from(value: i64) -> Self {
+        match value {
+            0 => Self::Everywhere,
+            1 => Self::Only,
+            2 => Self::Except,
+            _ => unreachable!(
+                "Invalid channel scope value. This means the database and rust struct are out of sync"
+            ),
+        }
+    }
+}
+
+/// The channels a trigger can be used in
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChannelFilter {
+    pub scope: ChannelScope,
+    /// Ignored if the scope is [`ChannelScope::Everywhere`]
+    pub channels: Vec<SlackChannelId>,
+}
+
+impl ChannelFilter {
+    /// Whether a trigger with this filter can be used in the channel
+    pub fn allows(&self, channel: &SlackChannelId) -> bool {
+        match self.scope {
+            ChannelScope::Everywhere => true,
+            ChannelScope::Only => self.channels.contains(channel),
+            ChannelScope::Except => !self.channels.contains(channel),
+        }
+    }
+
+    /// Whether there's a channel both filters allow, meaning triggers with them can overlap
+    pub fn overlaps(&self, other: &Self) -> bool {
+        match (self.scope, other.scope) {
+            (ChannelScope::Only, _) => self.channels.iter().any(|channel| other.allows(channel)),
+            (_, ChannelScope::Only) => other.channels.iter().any(|channel| self.allows(channel)),
+            // There's always some channel neither list excludes
+            (ChannelScope::Everywhere | ChannelScope::Except, _) => true,
+        }
+    }
+}
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+/// The trigger text can't be used for the trigger type
+pub enum InvalidTrigger {
+    /// The trigger can't be empty
+    Empty,
+    /// The regex is invalid: {0}
+    Regex(#[from] regex::Error),
+    /// The regex needs a capture group named `text` for the message content. E.g. `^J:(?<text>.*)$`
+    MissingContentGroup,
+    /// The paired trigger needs `text` to mark where the message content goes. E.g. `[text]`
+    MissingPlaceholder,
+    /// The paired trigger needs text on both sides of `text`. Use a prefix or suffix trigger instead
+    UnpairedTag,
+    /// The paired trigger can only contain `text` once, as the placeholder. E.g. `context:text` won't work
+    AmbiguousPlaceholder,
+}
+
+/// How strictly a trigger's text has to match a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchOptions {
+    /// Whether letter case has to match (e.g. whether "j:" matches "J: hello")
+    pub case_sensitive: bool,
+    /// Whether whitespace around the trigger text is ignored, and stripped with it
+    pub ignore_whitespace: bool,
+}
+
+impl Default for MatchOptions {
+    fn default() -> Self {
+        Self {
+            case_sensitive: true,
+            ignore_whitespace: false,
+        }
+    }
+}
+
+impl MatchOptions {
+    /// Shrinks a range of the message to exclude surrounding whitespace, if whitespace is ignored
+    pub fn trim(self, message: &str, range: Range<usize>) -> Range<usize> {
+        if !self.ignore_whitespace {
+            return range;
+        }
+
+        let content = &message[range.clone()];
+        let start = range.start + (content.len() - content.trim_start().len());
+
+        start..start + content.trim().len()
+    }
+
+    fn chars_match(self, a: char, b: char) -> bool {
+        a == b || (!self.case_sensitive && a.to_lowercase().eq(b.to_lowercase()))
+    }
+}
+
+/// Matches `tag` at the start of `message`, returning where the match ends
+fn match_start(message: &str, tag: &str, options: MatchOptions) -> Option<usize> {
+    let mut chars = message.char_indices();
+
+    for tag_char in tag.chars() {
+        let (_, char) = chars.next()?;
+
+        if !options.chars_match(char, tag_char) {
+            return None;
+        }
+    }
+
+    Some(chars.next().map_or(message.len(), |(i, _)| i))
+}
+
+/// Matches `tag` at the end of `message`, returning where the match starts
+fn match_end(message: &str, tag: &str, options: MatchOptions) -> Option<usize> {
+    let mut chars = message.char_indices().rev();
+    let mut start = message.len();
+
+    for tag_char in tag.chars().rev() {
+        let (i, char) = chars.next()?;
+
+        if !options.chars_match(char, tag_char) {
+            return None;
+        }
+
+        start = i;
+    }
+
+    Some(start)
 }
 
 impl From<i64> for Type {
@@ -104,6 +512,8 @@ impl From<i64> for Type {
         match value {
             0 => Self::Suffix,
             1 => Self::Prefix,
+            2 => Self::Regex,
+            3 => Self::Paired,
             _ => unreachable!(
                 "Invalid type value. This means the database and rust struct are out of sync"
             ),
@@ -122,6 +532,8 @@ impl FromStr for Type {
         match s {
             "suffix" => Ok(Self::Suffix),
             "prefix" => Ok(Self::Prefix),
+            "regex" => Ok(Self::Regex),
+            "paired" => Ok(Self::Paired),
             _ => Err(UnknownType(s.to_string())),
         }
     }
@@ -135,9 +547,52 @@ pub struct Trigger {
     pub system_id: system::Id<Trusted>,
     pub text: String,
     pub typ: Type,
+    pub case_sensitive: bool,
+    pub ignore_whitespace: bool,
+    pub channel_scope: ChannelScope,
 }
 
 impl Trigger {
+    pub const fn options(&self) -> MatchOptions {
+        MatchOptions {
+            case_sensitive: self.case_sensitive,
+            ignore_whitespace: self.ignore_whitespace,
+        }
+    }
+
+    /// The trigger's channel filter, given the channels from [`Self::fetch_channels_by_system_id`]
+    pub fn channel_filter(&self, channels: &HashMap<i64, Vec<SlackChannelId>>) -> ChannelFilter {
+        ChannelFilter {
+            scope: self.channel_scope,
+            channels: channels.get(&self.id.id).cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Checks whether this trigger overlaps with another, see [`Type::overlaps`]
+    pub fn overlaps(&self, typ: Type, text: &str, options: MatchOptions) -> bool {
+        // If either side ignores case/whitespace, it also matches the other side's text with any case/whitespace
+        let case_sensitive = self.case_sensitive && options.case_sensitive;
+        let ignore_whitespace = self.ignore_whitespace || options.ignore_whitespace;
+
+        let normalize = |text: &str| {
+            let text = util::unescape(text);
+            let text = if ignore_whitespace {
+                text.trim()
+            } else {
+                &text
+            };
+
+            if case_sensitive {
+                text.to_owned()
+            } else {
+                text.to_lowercase()
+            }
+        };
+
+        self.typ
+            .overlaps(&normalize(&self.text), typ, &normalize(text))
+    }
+
     #[tracing::instrument(skip(db))]
     pub async fn fetch_by_system_id(
         system_id: system::Id<Trusted>,
@@ -151,7 +606,10 @@ impl Trigger {
                     member_id as "member_id: member::Id<Trusted>",
                     system_id as "system_id: system::Id<Trusted>",
                     text,
-                    typ
+                    typ,
+                    case_sensitive as "case_sensitive: bool",
+                    ignore_whitespace as "ignore_whitespace: bool",
+                    channel_scope
                 FROM
                     triggers
                 WHERE
@@ -164,6 +622,42 @@ impl Trigger {
         .attach_printable("Error fetching triggers")
     }
 
+    /// Fetches the channels of every trigger in the system, keyed by trigger ID
+    #[tracing::instrument(skip(db))]
+    pub async fn fetch_channels_by_system_id(
+        system_id: system::Id<Trusted>,
+        db: &SqlitePool,
+    ) -> Result<HashMap<i64, Vec<SlackChannelId>>, sqlx::Error> {
+        let records = sqlx::query!(
+            r#"
+            SELECT
+                trigger_channels.trigger_id,
+                trigger_channels.channel_id
+            FROM
+                trigger_channels
+            JOIN
+                triggers ON triggers.id = trigger_channels.trigger_id
+            WHERE
+                triggers.system_id = $1
+            "#,
+            system_id
+        )
+        .fetch_all(db)
+        .await
+        .attach_printable("Error fetching trigger channels")?;
+
+        let mut channels = HashMap::<_, Vec<_>>::new();
+
+        for record in records {
+            channels
+                .entry(record.trigger_id)
+                .or_default()
+                .push(SlackChannelId::new(record.channel_id));
+        }
+
+        Ok(channels)
+    }
+
     #[tracing::instrument(skip(db))]
     pub async fn fetch_by_member_id(
         member_id: member::Id<Trusted>,
@@ -177,7 +671,10 @@ impl Trigger {
                 member_id as "member_id: member::Id<Trusted>",
                 system_id as "system_id: system::Id<Trusted>",
                 text,
-                typ
+                typ,
+                case_sensitive as "case_sensitive: bool",
+                ignore_whitespace as "ignore_whitespace: bool",
+                channel_scope
             FROM
                 triggers
             WHERE member_id = $1
@@ -195,27 +692,55 @@ impl Trigger {
         system_id: system::Id<Trusted>,
         typ: Type,
         content: String,
+        options: MatchOptions,
+        channels: &ChannelFilter,
         db: &SqlitePool,
     ) -> error_stack::Result<Self, sqlx::Error> {
-        sqlx::query_as!(
+        // The trigger and its channels are written together, so it's never usable in channels it shouldn't be
+        let mut transaction = db
+            .begin()
+            .await
+            .attach_printable("Failed to start transaction")?;
+
+        let trigger = sqlx::query_as!(
             Self,
             r#"
-            INSERT INTO triggers (member_id, system_id, typ, text)
-            VALUES ($1, $2, $3, $4)
+            INSERT INTO triggers (member_id, system_id, typ, text, case_sensitive, ignore_whitespace, channel_scope)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             RETURNING
                 id as "id: Id<Trusted>",
                 member_id as "member_id: member::Id<Trusted>",
                 system_id as "system_id: system::Id<Trusted>",
                 typ,
-                text
+                text,
+                case_sensitive as "case_sensitive: bool",
+                ignore_whitespace as "ignore_whitespace: bool",
+                channel_scope
             "#,
             member_id,
             system_id,
             typ,
-            content
+            content,
+            options.case_sensitive,
+            options.ignore_whitespace,
+            channels.scope
         )
-        .fetch_one(db)
+        .fetch_one(&mut *transaction)
         .await
-        .attach_printable("Failed to insert trigger into database")
+        .attach_printable("Failed to insert trigger into database")?;
+
+        trigger
+            .id
+            .write_channel_filter(channels, &mut transaction)
+            .await?;
+
+        transaction
+            .commit()
+            .await
+            .attach_printable("Failed to commit trigger")?;
+
+        matcher::invalidate(system_id);
+
+        Ok(trigger)
     }
 }
@@ -0,0 +1,150 @@
+//! Background jobs that run on a timer, rather than in response to a Slack event.
+//!
+//! Pending work is stored in the database (see [`ScheduledRevert`] and [`ScheduledCheckIn`]),
+//! so nothing is lost when the bot restarts.
+//! Anything that became due while the bot was down runs on the first check after it starts.
+
+use std::{sync::Arc, time::Duration};
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, error, info};
+
+use crate::{
+    BOT_TOKEN, announcements, interactions,
+    models::{check_in::ScheduledCheckIn, revert::ScheduledRevert, switch},
+    status,
+};
+
+/// How often the database is checked for due jobs
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum SchedulerError {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while calling the Slack API
+    Slack,
+}
+
+/// Runs due jobs forever. Meant to be spawned as its own task
+pub async fn run(client: Arc<SlackHyperClient>, db: SqlitePool) {
+    let mut interval = tokio::time::interval(POLL_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        interval.tick().await;
+
+        let reverts = match ScheduledRevert::fetch_due(&db).await {
+            Ok(reverts) => reverts,
+            Err(error) => {
+                error!(?error, "Error fetching due reverts");
+                continue;
+            }
+        };
+
+        // One failed revert shouldn't hold up everyone else's. It stays scheduled, so it's retried next time
+        for revert in reverts {
+            if let Err(error) = revert_front(&revert, &client, &db).await {
+                error!(?error, system_id = %revert.system_id, "Error reverting front");
+            }
+        }
+
+        send_check_ins(&client, &db).await;
+    }
+}
+
+/// Sends every due check-in reminder
+async fn send_check_ins(client: &SlackHyperClient, db: &SqlitePool) {
+    let check_ins = match ScheduledCheckIn::fetch_due(db).await {
+        Ok(check_ins) => check_ins,
+        Err(error) => {
+            error!(?error, "Error fetching due check-ins");
+            return;
+        }
+    };
+
+    // Unlike a revert, a failed reminder isn't retried, so a broken DM doesn't get re-sent every poll.
+    // The next one is scheduled by the next switch, or by the owner's answer to this one
+    for check_in in check_ins {
+        if let Err(error) = ScheduledCheckIn::delete(check_in.system_id, db).await {
+            error!(?error, system_id = %check_in.system_id, "Error deleting check-in");
+            continue;
+        }
+
+        if let Err(error) = interactions::check_in::send_reminder(&check_in, client, db).await {
+            error!(?error, system_id = %check_in.system_id, "Error sending check-in");
+        }
+    }
+}
+
+/// Switches a system to the base account and lets the owner know
+#[tracing::instrument(skip(client, db))]
+async fn revert_front(
+    revert: &ScheduledRevert,
+    client: &SlackHyperClient,
+    db: &SqlitePool,
+) -> Result<(), SchedulerError> {
+    let reverted = revert
+        .system_id
+        .switch_front(&[], revert.source, db)
+        .await
+        .change_context(SchedulerError::Sqlx)?;
+
+    // Switching only cancels the revert if the front changed
+    let mut connection = db
+        .acquire()
+        .await
+        .attach_printable("Failed to acquire connection")
+        .change_context(SchedulerError::Sqlx)?;
+
+    ScheduledRevert::cancel(revert.system_id, &mut connection)
+        .await
+        .change_context(SchedulerError::Sqlx)?;
+
+    if !reverted {
+        debug!("Already on the base account");
+        return Ok(());
+    }
+
+    info!("Reverted front to base account");
+
+    announcements::announce_switch(client, revert.system_id, revert.source, db).await;
+    status::mirror_front(client, revert.system_id, db).await;
+
+    let system = revert
+        .system_id
+        .fetch(db)
+        .await
+        .change_context(SchedulerError::Sqlx)?;
+
+    let text = match (revert.source, system.inactivity_timeout()) {
+        (switch::Source::Inactivity, Some(timeout)) => format!(
+            "You haven't sent a message in {}, so you've been switched back to your base account.",
+            crate::util::format_duration(timeout)
+        ),
+        _ => "Your timed switch is over, so you've been switched back to your base account."
+            .to_string(),
+    };
+
+    let session = client.open_session(&BOT_TOKEN);
+    let user: SlackUserId = system.owner_id.into();
+
+    let conversation = session
+        .conversations_open(&SlackApiConversationsOpenRequest::new().with_users(vec![user]))
+        .await
+        .change_context(SchedulerError::Slack)?
+        .channel;
+
+    session
+        .chat_post_message(&SlackApiChatPostMessageRequest::new(
+            conversation.id,
+            SlackMessageContent::new().with_text(text),
+        ))
+        .await
+        .change_context(SchedulerError::Slack)?;
+
+    Ok(())
+}
@@ -0,0 +1,160 @@
+//! Mirroring the front into the system owner's Slack status, and optionally their display name.
+//!
+//! The owner's own status is saved when mirroring starts (see [`SavedStatus`]) and put back when they switch to
+//! their base account. This uses the owner's OAuth token, which needs the `users.profile:write` scope.
+//! Systems created before mirroring was added need to run `/system reauth` to grant it.
+
+use error_stack::{Result, ResultExt};
+use slack_morphism::prelude::*;
+use sqlx::SqlitePool;
+use tracing::{debug, error};
+
+use crate::models::{self, member, saved_status::SavedStatus, system, trust::Trusted};
+
+/// The longest status text Slack allows
+const MAX_STATUS_TEXT_LENGTH: usize = 100;
+
+#[derive(thiserror::Error, displaydoc::Display, Debug)]
+pub enum StatusError {
+    /// Error while calling the database
+    Sqlx,
+    /// Error while updating the Slack profile
+    Slack,
+}
+
+/// Updates the owner's status to match the current front, logging any errors.
+/// Meant to be called after a switch that changed the front
+#[tracing::instrument(skip(client, db))]
+pub async fn mirror_front(
+    client: &SlackHyperClient,
+    system_id: system::Id<Trusted>,
+    db: &SqlitePool,
+) {
+    if let Err(error) = try_mirror_front(client, system_id, db).await {
+        error!(?error, "Error mirroring front into status");
+    }
+}
+
+/// Updates the owner's status to match the current front, if mirroring is on.
+/// If nobody is fronting, the owner's own status is put back instead
+pub async fn try_mirror_front(
+    client: &SlackHyperClient,
+    system_id: system::Id<Trusted>,
+    db: &SqlitePool,
+) -> Result<(), StatusError> {
+    let system = system_id
+        .fetch(db)
+        .await
+        .change_context(StatusError::Sqlx)?;
+
+    if !system.mirror_status {
+        debug!("Status mirroring is off");
+        return Ok(());
+    }
+
+    let fronting_members = system_id
+        .fronting_members(db)
+        .await
+        .change_context(StatusError::Sqlx)?;
+
+    let (Some(primary), Some(front)) = (
+        fronting_members.first(),
+        member::describe_front(&fronting_members),
+    ) else {
+        return restore(client, &system, db).await;
+    };
+
+    let token = user_token(&system);
+    let session = client.open_session(&token);
+
+    let saved = SavedStatus::fetch(system_id, db)
+        .await
+        .change_context(StatusError::Sqlx)?;
+
+    // Only the status from before mirroring started is saved, not one that's already mirroring
+    if saved.is_none_or(|saved| system.mirror_display_name && saved.display_name.is_none()) {
+        let profile = session
+            .users_profile_get(&SlackApiUsersProfileGetRequest::new())
+            .await
+            .change_context(StatusError::Slack)?
+            .profile;
+
+        SavedStatus::save(
+            system_id,
+            profile.status_text.as_deref().unwrap_or_default(),
+            profile
+                .status_emoji
+                .as_ref()
+                .map_or("", |emoji| emoji.0.as_str()),
+            profile
+                .display_name
+                .as_deref()
+                .filter(|_| system.mirror_display_name),
+            db,
+        )
+        .await
+        .change_context(StatusError::Sqlx)?;
+    }
+
+    let status_text = format!("Fronting: {front}")
+        .chars()
+        .take(MAX_STATUS_TEXT_LENGTH)
+        .collect::<String>();
+
+    let profile = SlackUserProfile::new()
+        .with_status_text(status_text)
+        .with_status_emoji(SlackEmoji(system.status_emoji.clone()));
+    let profile = if system.mirror_display_name {
+        profile.with_display_name(primary.display_name.clone())
+    } else {
+        profile
+    };
+
+    session
+        .users_profile_set(&SlackApiUsersProfileSetRequest::new(profile))
+        .await
+        .change_context(StatusError::Slack)?;
+
+    debug!("Mirrored front into status");
+    Ok(())
+}
+
+/// Puts the owner's own status back, if it was saved
+pub async fn restore(
+    client: &SlackHyperClient,
+    system: &models::System,
+    db: &SqlitePool,
+) -> Result<(), StatusError> {
+    let Some(saved) = SavedStatus::fetch(system.id, db)
+        .await
+        .change_context(StatusError::Sqlx)?
+    else {
+        debug!("No saved status to restore");
+        return Ok(());
+    };
+
+    let token = user_token(system);
+
+    client
+        .open_session(&token)
+        .users_profile_set(&SlackApiUsersProfileSetRequest::new(
+            SlackUserProfile::new()
+                .with_status_text(saved.status_text)
+                .with_status_emoji(SlackEmoji(saved.status_emoji))
+                .opt_display_name(saved.display_name),
+        ))
+        .await
+        .change_context(StatusError::Slack)?;
+
+    SavedStatus::delete(system.id, db)
+        .await
+        .change_context(StatusError::Sqlx)?;
+
+    debug!("Restored saved status");
+    Ok(())
+}
+
+fn user_token(system: &models::System) -> SlackApiToken {
+    SlackApiToken::new(system.slack_oauth_token.expose().into())
+        .with_token_type(SlackApiTokenType::User)
+}
@@ -1,3 +1,5 @@
+use slack_morphism::SlackChannelId;
+
 /// Records one or more fields in the current span.
 ///
 /// Use % for recording a field with a [`Display`] value.
@@ -52,3 +54,120 @@ macro_rules! fields {
     // end
     () => {}
 }
+
+/// Reverses Slack's escaping of the `&`, `<` and `>` control characters.
+///
+/// Slack escapes these in message text and slash command arguments, but not in rich text blocks.
+/// See <https://api.slack.com/reference/surfaces/formatting#escaping>
+pub fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+/// Escapes the `&`, `<` and `>` control characters the same way Slack does in message text.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Parses a channel mention (e.g. `<#C0123456|general>`) or a raw channel ID into a channel ID.
+///
+/// Meant to be used as a clap value parser.
+pub fn parse_slack_channel_id(text: &str) -> Result<SlackChannelId, String> {
+    let id = text
+        .strip_prefix("<#")
+        .and_then(|mention| mention.strip_suffix('>'))
+        .and_then(|mention| mention.split('|').next())
+        .unwrap_or(text);
+
+    if id.starts_with(['C', 'G']) && id.chars().all(|c| c.is_ascii_alphanumeric()) {
+        Ok(SlackChannelId::new(id.to_string()))
+    } else {
+        Err(format!(
+            "{text} is not a channel. Mention it with #channel-name"
+        ))
+    }
+}
+
+/// Parses a duration like `30m`, `12h`, `7d`, `2w` or a combination like `1h30m`.
+///
+/// Meant to be used as a clap value parser.
+pub fn parse_duration(text: &str) -> Result<time::Duration, String> {
+    let invalid = || format!("{text} is not a duration. Use e.g. `30m`, `12h`, `7d` or `1h30m`");
+
+    let mut total = time::Duration::ZERO;
+    let mut rest = text.trim();
+
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let amount = rest[..digits].parse::<i64>().map_err(|_| invalid())?;
+        let unit = rest[digits..].chars().next().ok_or_else(invalid)?;
+
+        let unit_seconds = match unit {
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+
+        total = amount
+            .checked_mul(unit_seconds)
+            .map(time::Duration::seconds)
+            .and_then(|duration| total.checked_add(duration))
+            .ok_or_else(|| format!("{text} is too long"))?;
+        rest = &rest[digits + unit.len_utf8()..];
+    }
+
+    Ok(total)
+}
+
+/// Parses a timeout setting: either a duration (see [`parse_duration`]) or `off`, which is parsed as zero
+pub fn parse_timeout(text: &str) -> Result<time::Duration, String> {
+    if text.eq_ignore_ascii_case("off") {
+        return Ok(time::Duration::ZERO);
+    }
+
+    parse_duration(text)
+}
+
+/// Formats a duration with its two largest units, e.g. `2d 3h` or `45m`
+pub fn format_duration(duration: time::Duration) -> String {
+    let parts = [
+        (duration.whole_days(), "d"),
+        (duration.whole_hours() % 24, "h"),
+        (duration.whole_minutes() % 60, "m"),
+    ];
+
+    let formatted = parts
+        .iter()
+        .skip_while(|(amount, _)| *amount == 0)
+        .take(2)
+        .filter(|(amount, _)| *amount != 0)
+        .map(|(amount, unit)| format!("{amount}{unit}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if formatted.is_empty() {
+        "under a minute".to_owned()
+    } else {
+        formatted
+    }
+}
+
+/// Formats a UTC time for Slack, so it's shown in the reader's own timezone
+pub fn slack_date(time: time::PrimitiveDateTime) -> String {
+    format!(
+        "<!date^{}^{{date_short_pretty}} at {{time}}|{} UTC>",
+        time.assume_utc().unix_timestamp(),
+        time
+    )
+}